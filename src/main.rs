@@ -20,11 +20,38 @@ use solvers::{
 struct Args {
     day: Option<u8>,
     input_path: Option<String>,
+    /// Print a visualization of the solution if the day supports it.
+    #[arg(short, long)]
+    visualize: bool,
+    /// File with the rock shapes for day 17, drawn with `#` and `.` and
+    /// separated by empty lines.
+    #[arg(long)]
+    rock_shapes: Option<String>,
+    /// Width of the chamber for day 17.
+    #[arg(long, default_value_t = day17::CHAMBER_WIDTH)]
+    chamber_width: usize,
+    /// Position `x,y` the sand pours in from for day 14.
+    #[arg(long, value_parser = day14::parse_point)]
+    sand_source: Option<day14::Point>,
+    /// Directory to write the frames of the day 14 sand simulation to as
+    /// PPM images.
+    #[arg(long)]
+    frames_dir: Option<String>,
+}
+
+/// Options that only apply to some days.
+#[derive(Clone, Debug)]
+struct DayOptions {
+    rock_shapes: Option<String>,
+    chamber_width: usize,
+    sand_source: Option<day14::Point>,
+    frames_dir: Option<String>,
 }
 
 trait SolveDisplayable {
     fn solve_part1(&self) -> anyhow::Result<Box<dyn Display + Send>>;
     fn solve_part2(&self) -> anyhow::Result<Option<Box<dyn Display + Send>>>;
+    fn visualize(&self) -> anyhow::Result<Option<String>>;
 }
 
 struct DisplayDecorator<'a, S: AocSolver<'a, T1, T2>, T1, T2> {
@@ -48,6 +75,10 @@ impl<'a, S: AocSolver<'a, T1, T2>, T1: Display + Send + 'static, T2: Display + S
             Ok(None)
         }
     }
+
+    fn visualize(&self) -> anyhow::Result<Option<String>> {
+        self.solver.visualize()
+    }
 }
 
 impl<'a, S: AocSolver<'a, T1, T2>, T1, T2> From<S> for DisplayDecorator<'a, S, T1, T2> {
@@ -65,12 +96,24 @@ impl<'a, S: AocSolver<'a, T1, T2>, T1, T2> From<S> for DisplayDecorator<'a, S, T
 async fn main() -> anyhow::Result<()> {
     let time_start = Instant::now();
     let args = Args::parse();
+    let options = DayOptions {
+        rock_shapes: args.rock_shapes,
+        chamber_width: args.chamber_width,
+        sand_source: args.sand_source,
+        frames_dir: args.frames_dir,
+    };
 
     if let Some(day) = args.day {
-        print!("{}", solve_day(day, args.input_path).await?);
+        print!(
+            "{}",
+            solve_day(day, args.input_path, args.visualize, options).await?
+        );
     } else {
         let tasks: Vec<_> = (1..=25)
-            .map(|day| tokio::spawn(async move { solve_day(day, None).await }))
+            .map(|day| {
+                let options = options.clone();
+                tokio::spawn(async move { solve_day(day, None, args.visualize, options).await })
+            })
             .collect();
         for task in tasks {
             print!("{}", task.await??);
@@ -90,6 +133,7 @@ struct SolvedDay {
     time_part2_solved: Instant,
     solution_part1: Box<dyn Display + Send>,
     solution_part2: Option<Box<dyn Display + Send>>,
+    visualization: Option<String>,
 }
 
 impl Display for SolvedDay {
@@ -122,11 +166,19 @@ impl Display for SolvedDay {
                 Style::new().bold().paint(part2.to_string())
             ))?;
         }
+        if let Some(visualization) = &self.visualization {
+            f.write_fmt(format_args!("Visualization:\n{}\n", visualization))?;
+        }
         Ok(())
     }
 }
 
-async fn solve_day(day: u8, input_path: Option<String>) -> anyhow::Result<SolvedDay> {
+async fn solve_day(
+    day: u8,
+    input_path: Option<String>,
+    visualize: bool,
+    options: DayOptions,
+) -> anyhow::Result<SolvedDay> {
     let time_start = Instant::now();
     let input_path = input_path.unwrap_or_else(|| format!("./day{:0>2}", day));
     let input = tokio::fs::read(&input_path).await.context(input_path)?;
@@ -147,10 +199,27 @@ async fn solve_day(day: u8, input_path: Option<String>) -> anyhow::Result<Solved
         11 => Box::<DisplayDecorator<_, _, _>>::new(day11::Solver::new(input)?.into()),
         12 => Box::<DisplayDecorator<_, _, _>>::new(day12::Solver::new(input)?.into()),
         13 => Box::<DisplayDecorator<_, _, _>>::new(day13::Solver::new(input)?.into()),
-        14 => Box::<DisplayDecorator<_, _, _>>::new(day14::Solver::new(input)?.into()),
+        14 => {
+            let solver = day14::Solver::with_source(
+                input,
+                options.sand_source.unwrap_or(day14::SAND_SOURCE),
+            )?;
+            if let Some(dir) = &options.frames_dir {
+                write_sand_frames(&solver, dir).await?;
+            }
+            Box::<DisplayDecorator<_, _, _>>::new(solver.into())
+        }
         15 => Box::<DisplayDecorator<_, _, _>>::new(day15::Solver::new(input)?.into()),
         16 => Box::<DisplayDecorator<_, _, _>>::new(day16::Solver::new(input)?.into()),
-        17 => Box::<DisplayDecorator<_, _, _>>::new(day17::Solver::new(input)?.into()),
+        17 => {
+            let rock_shapes = match options.rock_shapes {
+                Some(path) => tokio::fs::read_to_string(&path).await.context(path)?,
+                None => day17::DEFAULT_ROCK_SHAPES.into(),
+            };
+            Box::<DisplayDecorator<_, _, _>>::new(
+                day17::Solver::with_config(input, &rock_shapes, options.chamber_width)?.into(),
+            )
+        }
         18 => Box::<DisplayDecorator<_, _, _>>::new(day18::Solver::new(input)?.into()),
         19 => Box::<DisplayDecorator<_, _, _>>::new(day19::Solver::new(input)?.into()),
         20 => Box::<DisplayDecorator<_, _, _>>::new(day20::Solver::new(input)?.into()),
@@ -169,6 +238,8 @@ async fn solve_day(day: u8, input_path: Option<String>) -> anyhow::Result<Solved
     let solution_part2 = solver.solve_part2()?;
     let time_part2_solved = Instant::now();
 
+    let visualization = if visualize { solver.visualize()? } else { None };
+
     Ok(SolvedDay {
         day,
         time_start,
@@ -178,5 +249,22 @@ async fn solve_day(day: u8, input_path: Option<String>) -> anyhow::Result<Solved
         time_part2_solved,
         solution_part1,
         solution_part2,
+        visualization,
     })
 }
+
+/// Writes one image per `day14::GRAINS_PER_FRAME` grains of sand, numbered
+/// so that the file names sort in order.
+async fn write_sand_frames(solver: &day14::Solver, dir: &str) -> anyhow::Result<()> {
+    tokio::fs::create_dir_all(dir)
+        .await
+        .context(dir.to_string())?;
+    let mut cave = solver.cave();
+    for (i, frame) in cave.frames(day14::GRAINS_PER_FRAME).enumerate() {
+        let path = format!("{}/frame{:04}.ppm", dir, i);
+        tokio::fs::write(&path, frame.to_netpbm(true))
+            .await
+            .context(path)?;
+    }
+    Ok(())
+}
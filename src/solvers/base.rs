@@ -4,4 +4,8 @@ pub trait AocSolver<'a, T1, T2> {
         Self: Sized;
     fn solve_part1(&self) -> anyhow::Result<T1>;
     fn solve_part2(&self) -> anyhow::Result<Option<T2>>;
+
+    fn visualize(&self) -> anyhow::Result<Option<String>> {
+        Ok(None)
+    }
 }
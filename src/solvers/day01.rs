@@ -37,7 +37,7 @@ impl<T: Copy + Default + PartialOrd, const K: usize> TopK<T, K> {
     }
 
     pub fn peek(&self) -> Option<&T> {
-        self.k_largest.get(0)
+        self.k_largest.first()
     }
 }
 
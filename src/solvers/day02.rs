@@ -1,10 +1,32 @@
+use std::fmt::Display;
+
 use super::{base::AocSolver, error::InputParseError};
 
-#[derive(Clone, Copy, Debug)]
-enum Shape {
-    Rock,
-    Paper,
-    Scissors,
+type Score = u64;
+
+/// Shape given by its index within a `Game`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Shape(usize);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Lost,
+    Draw,
+    Won,
+}
+
+impl Outcome {
+    const ALL: [Outcome; 3] = [Outcome::Lost, Outcome::Draw, Outcome::Won];
+}
+
+impl Display for Outcome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Outcome::Lost => "lose",
+            Outcome::Draw => "draw",
+            Outcome::Won => "win",
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -24,105 +46,299 @@ impl std::fmt::Display for EnumParseError {
 
 impl std::error::Error for EnumParseError {}
 
-impl TryFrom<u8> for Shape {
-    type Error = EnumParseError;
+/// Cyclic game with an odd number of shapes, in which each shape beats the
+/// `(n - 1) / 2` shapes preceding it (wrapping around). Rock, paper,
+/// scissors is the game with three shapes in this order.
+pub struct Game {
+    names: Vec<String>,
+    shape_scores: Vec<Score>,
+    /// Scores in the order of `Outcome::ALL`.
+    outcome_scores: [Score; 3],
+}
 
-    fn try_from(value: u8) -> Result<Self, Self::Error> {
-        match value {
-            b'A' | b'X' => Ok(Self::Rock),
-            b'B' | b'Y' => Ok(Self::Paper),
-            b'C' | b'Z' => Ok(Self::Scissors),
-            _ => Err(EnumParseError {
-                value,
-                enum_name: "Shape",
-            }),
+impl Game {
+    pub fn new(
+        names: &[&str],
+        shape_scores: &[Score],
+        outcome_scores: [Score; 3],
+    ) -> anyhow::Result<Self> {
+        if names.len() < 3 || names.len().is_multiple_of(2) || names.len() > 25 {
+            anyhow::bail!("a cyclic game needs an odd number of 3 to 25 shapes");
         }
+        if names.len() != shape_scores.len() {
+            anyhow::bail!("expected a score for each of the {} shapes", names.len());
+        }
+        Ok(Self {
+            names: names.iter().map(|name| name.to_string()).collect(),
+            shape_scores: shape_scores.into(),
+            outcome_scores,
+        })
     }
-}
 
-type Score = u64;
+    pub fn rock_paper_scissors() -> Self {
+        Self::new(&["Rock", "Paper", "Scissors"], &[1, 2, 3], [0, 3, 6])
+            .expect("invalid rock, paper, scissors definition")
+    }
 
-impl Shape {
-    fn score(&self) -> Score {
-        match self {
-            Self::Rock => 1,
-            Self::Paper => 2,
-            Self::Scissors => 3,
-        }
+    fn num_shapes(&self) -> usize {
+        self.names.len()
     }
 
-    fn outcome_against(&self, other: Self) -> Outcome {
-        use Shape::*;
-        match (self, other) {
-            (Rock, Paper) => Outcome::Lost,
-            (Rock, Scissors) => Outcome::Won,
-            (Paper, Rock) => Outcome::Won,
-            (Paper, Scissors) => Outcome::Lost,
-            (Scissors, Rock) => Outcome::Lost,
-            (Scissors, Paper) => Outcome::Won,
-            _ => Outcome::Draw,
+    pub fn outcome(&self, me: Shape, opponent: Shape) -> Outcome {
+        let n = self.num_shapes();
+        match (me.0 + n - opponent.0) % n {
+            0 => Outcome::Draw,
+            distance if distance <= n / 2 => Outcome::Won,
+            _ => Outcome::Lost,
         }
     }
-}
 
-#[derive(Clone, Copy, Debug)]
-enum Outcome {
-    Won,
-    Draw,
-    Lost,
-}
+    pub fn score(&self, opponent: Shape, me: Shape) -> Score {
+        self.shape_scores[me.0] + self.outcome_scores[self.outcome(me, opponent) as usize]
+    }
 
-impl Outcome {
-    fn score(&self) -> Score {
-        match self {
-            Self::Won => 6,
-            Self::Draw => 3,
-            Self::Lost => 0,
-        }
+    /// Highest scoring shape that leads to the desired outcome.
+    pub fn choose(&self, opponent: Shape, desired_outcome: Outcome) -> Shape {
+        (0..self.num_shapes())
+            .map(Shape)
+            .filter(|&me| self.outcome(me, opponent) == desired_outcome)
+            .max_by_key(|&me| self.shape_scores[me.0])
+            .expect("every outcome is possible in a cyclic game")
     }
-}
 
-impl TryFrom<u8> for Outcome {
-    type Error = EnumParseError;
+    /// Parses the opponent's column, which uses letters from `A`.
+    fn parse_opponent(&self, value: u8) -> Result<Shape, EnumParseError> {
+        (value as usize)
+            .checked_sub(b'A' as usize)
+            .filter(|&i| i < self.num_shapes())
+            .map(Shape)
+            .ok_or(EnumParseError {
+                value,
+                enum_name: "Shape",
+            })
+    }
 
-    fn try_from(value: u8) -> Result<Self, Self::Error> {
-        match value {
-            b'X' => Ok(Self::Lost),
-            b'Y' => Ok(Self::Draw),
-            b'Z' => Ok(Self::Won),
-            _ => Err(EnumParseError {
+    /// Parses the strategy column, which uses letters ending with `Z`.
+    fn parse_strategy(&self, value: u8) -> Result<usize, EnumParseError> {
+        (value as usize + self.num_shapes())
+            .checked_sub(b'Z' as usize + 1)
+            .filter(|&i| i < self.num_shapes())
+            .ok_or(EnumParseError {
                 value,
-                enum_name: "Outcome",
-            }),
+                enum_name: "strategy",
+            })
+    }
+
+    fn strategy_letter(&self, index: usize) -> char {
+        (b'Z' + 1 - self.num_shapes() as u8 + index as u8) as char
+    }
+
+    pub fn total(&self, guide: &[Instruction], interpretation: &Interpretation) -> Score {
+        guide
+            .iter()
+            .map(|&(opponent, strategy)| {
+                let me = match interpretation {
+                    Interpretation::Shapes(shapes) => shapes[strategy],
+                    Interpretation::Outcomes(outcomes) => self.choose(opponent, outcomes[strategy]),
+                };
+                self.score(opponent, me)
+            })
+            .sum()
+    }
+
+    /// Finds the best and worst mapping of the strategy letters to shapes
+    /// and, if there are three letters, to outcomes. The total score is a
+    /// sum of scores per letter, so each mapping is an assignment problem.
+    pub fn analyze(&self, guide: &[Instruction]) -> Analysis {
+        let n = self.num_shapes();
+        let mut counts = vec![vec![0; n]; n];
+        for &(opponent, strategy) in guide {
+            counts[strategy][opponent.0] += 1;
         }
+        let letter_scores = |score: &dyn Fn(Shape, usize) -> Score, num_meanings: usize| {
+            counts
+                .iter()
+                .map(|counts| {
+                    (0..num_meanings)
+                        .map(|meaning| {
+                            (0..n)
+                                .map(|opponent| counts[opponent] * score(Shape(opponent), meaning))
+                                .sum()
+                        })
+                        .collect()
+                })
+                .collect::<Vec<Vec<Score>>>()
+        };
+
+        let shape_scores = letter_scores(&|opponent, me| self.score(opponent, Shape(me)), n);
+        let (highest, lowest) = extreme_assignments(&shape_scores);
+        let to_shapes = |(assignment, total): (Vec<usize>, Score)| ScoredInterpretation {
+            interpretation: Interpretation::Shapes(assignment.into_iter().map(Shape).collect()),
+            total,
+        };
+        let mut analysis = Analysis {
+            best: to_shapes(highest),
+            worst: to_shapes(lowest),
+        };
+
+        if n == Outcome::ALL.len() {
+            let outcome_scores = letter_scores(
+                &|opponent, outcome| {
+                    self.score(opponent, self.choose(opponent, Outcome::ALL[outcome]))
+                },
+                Outcome::ALL.len(),
+            );
+            let (highest, lowest) = extreme_assignments(&outcome_scores);
+            let to_outcomes = |(assignment, total): (Vec<usize>, Score)| ScoredInterpretation {
+                interpretation: Interpretation::Outcomes(
+                    assignment.into_iter().map(|i| Outcome::ALL[i]).collect(),
+                ),
+                total,
+            };
+            if highest.1 > analysis.best.total {
+                analysis.best = to_outcomes(highest);
+            }
+            if lowest.1 < analysis.worst.total {
+                analysis.worst = to_outcomes(lowest);
+            }
+        }
+        analysis
+    }
+
+    pub fn describe(&self, interpretation: &Interpretation) -> String {
+        let meanings: Vec<String> = match interpretation {
+            Interpretation::Shapes(shapes) => shapes
+                .iter()
+                .map(|shape| self.names[shape.0].clone())
+                .collect(),
+            Interpretation::Outcomes(outcomes) => outcomes.iter().map(Outcome::to_string).collect(),
+        };
+        meanings
+            .iter()
+            .enumerate()
+            .map(|(i, meaning)| format!("{}={}", self.strategy_letter(i), meaning))
+            .collect::<Vec<String>>()
+            .join(", ")
     }
 }
 
-struct Tally(Score);
+/// Mappings of letters to distinct meanings with the highest and lowest
+/// total score, given the score of each letter for each meaning.
+fn extreme_assignments(scores: &[Vec<Score>]) -> ((Vec<usize>, Score), (Vec<usize>, Score)) {
+    let total = |assignment: &[usize]| {
+        assignment
+            .iter()
+            .enumerate()
+            .map(|(letter, &meaning)| scores[letter][meaning])
+            .sum()
+    };
+    let costs: Vec<Vec<i64>> = scores
+        .iter()
+        .map(|row| row.iter().map(|&score| score as i64).collect())
+        .collect();
+    let negated: Vec<Vec<i64>> = costs
+        .iter()
+        .map(|row| row.iter().map(|&cost| -cost).collect())
+        .collect();
+    let highest = min_cost_assignment(&negated);
+    let lowest = min_cost_assignment(&costs);
+    let (highest_total, lowest_total) = (total(&highest), total(&lowest));
+    ((highest, highest_total), (lowest, lowest_total))
+}
 
-impl Tally {
-    fn new() -> Self {
-        Self(0)
+/// Assigns each row of a square cost matrix to a distinct column with the
+/// smallest total cost, using the Hungarian algorithm. Returns the column of
+/// each row.
+fn min_cost_assignment(costs: &[Vec<i64>]) -> Vec<usize> {
+    // Rows and columns are counted from 1, with 0 as a virtual column.
+    let n = costs.len();
+    let mut row_potential = vec![0; n + 1];
+    let mut column_potential = vec![0; n + 1];
+    let mut row_of_column = vec![0; n + 1];
+    let mut previous_column = vec![0; n + 1];
+    for row in 1..=n {
+        row_of_column[0] = row;
+        let mut column = 0;
+        let mut min_slack = vec![i64::MAX; n + 1];
+        let mut visited = vec![false; n + 1];
+        loop {
+            visited[column] = true;
+            let current_row = row_of_column[column];
+            let mut delta = i64::MAX;
+            let mut next_column = 0;
+            for j in 1..=n {
+                if visited[j] {
+                    continue;
+                }
+                let slack = costs[current_row - 1][j - 1]
+                    - row_potential[current_row]
+                    - column_potential[j];
+                if slack < min_slack[j] {
+                    min_slack[j] = slack;
+                    previous_column[j] = column;
+                }
+                if min_slack[j] < delta {
+                    delta = min_slack[j];
+                    next_column = j;
+                }
+            }
+            for j in 0..=n {
+                if visited[j] {
+                    row_potential[row_of_column[j]] += delta;
+                    column_potential[j] -= delta;
+                } else {
+                    min_slack[j] -= delta;
+                }
+            }
+            column = next_column;
+            if row_of_column[column] == 0 {
+                break;
+            }
+        }
+        while column != 0 {
+            let previous = previous_column[column];
+            row_of_column[column] = row_of_column[previous];
+            column = previous;
+        }
     }
 
-    fn tally_round(&mut self, opponent: Shape, me: Shape) {
-        self.0 += me.score() + me.outcome_against(opponent).score();
+    let mut assignment = vec![0; n];
+    for column in 1..=n {
+        assignment[row_of_column[column] - 1] = column - 1;
     }
+    assignment
+}
 
-    fn total(&self) -> Score {
-        self.0
-    }
+/// Meaning of the strategy letters, in order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Interpretation {
+    Shapes(Vec<Shape>),
+    Outcomes(Vec<Outcome>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScoredInterpretation {
+    pub interpretation: Interpretation,
+    pub total: Score,
 }
 
-type Instruction = (Shape, u8);
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Analysis {
+    pub best: ScoredInterpretation,
+    pub worst: ScoredInterpretation,
+}
+
+type Instruction = (Shape, usize);
 type StrategyGuide = Vec<Instruction>;
 
 pub struct Solver {
+    game: Game,
     strategy_guide: StrategyGuide,
 }
 
 impl AocSolver<'_, u64, u64> for Solver {
     fn new(input: &str) -> anyhow::Result<Self> {
+        let game = Game::rock_paper_scissors();
         Ok(Self {
             strategy_guide: input
                 .lines()
@@ -130,33 +346,41 @@ impl AocSolver<'_, u64, u64> for Solver {
                     if line.is_empty() {
                         return None;
                     }
-                    Some(Self::parse_line(line.as_bytes()))
+                    Some(Self::parse_line(&game, line.as_bytes()))
                 })
                 .collect::<anyhow::Result<StrategyGuide>>()?,
+            game,
         })
     }
 
     fn solve_part1(&self) -> anyhow::Result<u64> {
-        let mut tally = Tally::new();
-        for (opponent, me) in &self.strategy_guide {
-            let me = Shape::try_from(*me)?;
-            tally.tally_round(*opponent, me);
-        }
-        Ok(tally.total())
+        let shapes = (0..3).map(Shape).collect();
+        Ok(self
+            .game
+            .total(&self.strategy_guide, &Interpretation::Shapes(shapes)))
     }
 
     fn solve_part2(&self) -> anyhow::Result<Option<u64>> {
-        let mut tally = Tally::new();
-        for (opponent, desired_outcome) in &self.strategy_guide {
-            let desired_outcome = Outcome::try_from(*desired_outcome)?;
-            tally.tally_round(*opponent, Self::choose_part2(*opponent, desired_outcome));
-        }
-        Ok(Some(tally.total()))
+        Ok(Some(self.game.total(
+            &self.strategy_guide,
+            &Interpretation::Outcomes(Outcome::ALL.into()),
+        )))
+    }
+
+    fn visualize(&self) -> anyhow::Result<Option<String>> {
+        let analysis = self.game.analyze(&self.strategy_guide);
+        Ok(Some(format!(
+            "Best: {} ({})\nWorst: {} ({})\n",
+            analysis.best.total,
+            self.game.describe(&analysis.best.interpretation),
+            analysis.worst.total,
+            self.game.describe(&analysis.worst.interpretation)
+        )))
     }
 }
 
 impl Solver {
-    fn parse_line(line: &[u8]) -> anyhow::Result<Instruction> {
+    fn parse_line(game: &Game, line: &[u8]) -> anyhow::Result<Instruction> {
         let mut chars = line.iter().copied();
         let mut next_char = || {
             chars
@@ -164,51 +388,169 @@ impl Solver {
                 .ok_or_else(|| InputParseError::new("line too short".into()))
         };
 
-        let opponent = Shape::try_from(next_char()?)?;
+        let opponent = game.parse_opponent(next_char()?)?;
         if let Ok(char) = next_char() {
             if char != b' ' {
                 return Err(InputParseError::new("missing or invalid separator".into()).into());
             }
         }
-        let strategy = next_char()?;
+        let strategy = game.parse_strategy(next_char()?)?;
         Ok((opponent, strategy))
     }
-
-    fn choose_part2(opponent: Shape, desired_outcome: Outcome) -> Shape {
-        match desired_outcome {
-            Outcome::Draw => opponent,
-            Outcome::Won => Self::winning_shape_against(opponent),
-            Outcome::Lost => Self::losing_shape_against(opponent),
-        }
-    }
-
-    fn winning_shape_against(shape: Shape) -> Shape {
-        use Shape::*;
-        match shape {
-            Rock => Paper,
-            Paper => Scissors,
-            Scissors => Rock,
-        }
-    }
-
-    fn losing_shape_against(shape: Shape) -> Shape {
-        use Shape::*;
-        match shape {
-            Rock => Scissors,
-            Paper => Rock,
-            Scissors => Paper,
-        }
-    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::solvers::test::test_example_input;
+    use rstest::rstest;
 
     #[test]
     fn test_example() {
         let input = include_str!("examples/day02");
         test_example_input::<Solver, _, _>(input, 15, Some(12));
     }
+
+    #[test]
+    fn test_analysis() {
+        let solver = Solver::new(include_str!("examples/day02")).unwrap();
+        let analysis = solver.game.analyze(&solver.strategy_guide);
+        assert_eq!(analysis.best.total, 24);
+        assert_eq!(
+            solver.game.describe(&analysis.best.interpretation),
+            "X=Scissors, Y=Paper, Z=Rock"
+        );
+        assert_eq!(analysis.worst.total, 6);
+        assert_eq!(
+            solver.game.describe(&analysis.worst.interpretation),
+            "X=Rock, Y=Scissors, Z=Paper"
+        );
+    }
+
+    fn rock_paper_scissors_lizard_spock() -> Game {
+        Game::new(
+            &["Rock", "Spock", "Paper", "Lizard", "Scissors"],
+            &[1, 2, 3, 4, 5],
+            [0, 3, 6],
+        )
+        .unwrap()
+    }
+
+    #[rstest]
+    #[case(1, 0)]
+    #[case(2, 0)]
+    #[case(2, 1)]
+    #[case(3, 1)]
+    #[case(3, 2)]
+    #[case(4, 3)]
+    #[case(4, 2)]
+    #[case(0, 4)]
+    #[case(0, 3)]
+    #[case(1, 4)]
+    fn test_n_way_outcome(#[case] winner: usize, #[case] loser: usize) {
+        let game = rock_paper_scissors_lizard_spock();
+        assert_eq!(game.outcome(Shape(winner), Shape(loser)), Outcome::Won);
+        assert_eq!(game.outcome(Shape(loser), Shape(winner)), Outcome::Lost);
+        assert_eq!(game.outcome(Shape(winner), Shape(winner)), Outcome::Draw);
+    }
+
+    #[test]
+    fn test_n_way_game() {
+        let game = rock_paper_scissors_lizard_spock();
+        assert_eq!(game.choose(Shape(0), Outcome::Won), Shape(2));
+        assert_eq!(game.choose(Shape(0), Outcome::Lost), Shape(4));
+        assert_eq!(game.score(Shape(0), Shape(2)), 9);
+
+        let guide = vec![
+            (
+                game.parse_opponent(b'A').unwrap(),
+                game.parse_strategy(b'V').unwrap(),
+            ),
+            (
+                game.parse_opponent(b'E').unwrap(),
+                game.parse_strategy(b'Z').unwrap(),
+            ),
+        ];
+        assert!(game.parse_opponent(b'F').is_err());
+        assert!(game.parse_strategy(b'U').is_err());
+        let analysis = game.analyze(&guide);
+        assert_eq!(analysis.best.total, 9 + 8);
+        assert_eq!(analysis.worst.total, 7);
+        assert_eq!(
+            game.describe(&Interpretation::Shapes((0..5).map(Shape).collect())),
+            "V=Rock, W=Spock, X=Paper, Y=Lizard, Z=Scissors"
+        );
+    }
+
+    #[rstest]
+    #[case(&["Rock", "Paper"], &[1, 2])]
+    #[case(&["A", "B", "C", "D"], &[1, 2, 3, 4])]
+    #[case(&["Rock", "Paper", "Scissors"], &[1, 2])]
+    fn test_invalid_game(#[case] names: &[&str], #[case] scores: &[Score]) {
+        assert!(Game::new(names, scores, [0, 3, 6]).is_err());
+    }
+
+    fn large_game(num_shapes: usize) -> Game {
+        let names: Vec<String> = (0..num_shapes).map(|i| format!("S{}", i)).collect();
+        let names: Vec<&str> = names.iter().map(String::as_str).collect();
+        let scores: Vec<Score> = (0..num_shapes as Score).map(|i| (i * 7) % 11).collect();
+        Game::new(&names, &scores, [0, 3, 6]).unwrap()
+    }
+
+    fn large_guide(num_shapes: usize) -> Vec<Instruction> {
+        (0..200)
+            .map(|i| (Shape(i * 5 % num_shapes), i * i % num_shapes))
+            .collect()
+    }
+
+    #[test]
+    fn test_analysis_matches_exhaustive_search() {
+        fn permutations(items: &[usize]) -> Vec<Vec<usize>> {
+            if items.is_empty() {
+                return vec![vec![]];
+            }
+            let mut result = vec![];
+            for (i, &item) in items.iter().enumerate() {
+                let mut rest = items.to_vec();
+                rest.remove(i);
+                for mut permutation in permutations(&rest) {
+                    permutation.insert(0, item);
+                    result.push(permutation);
+                }
+            }
+            result
+        }
+
+        let game = large_game(7);
+        let guide = large_guide(7);
+        let totals: Vec<Score> = permutations(&(0..7).collect::<Vec<usize>>())
+            .into_iter()
+            .map(|shapes| {
+                game.total(
+                    &guide,
+                    &Interpretation::Shapes(shapes.into_iter().map(Shape).collect()),
+                )
+            })
+            .collect();
+        let analysis = game.analyze(&guide);
+        assert_eq!(analysis.best.total, *totals.iter().max().unwrap());
+        assert_eq!(analysis.worst.total, *totals.iter().min().unwrap());
+        assert_eq!(
+            game.total(&guide, &analysis.best.interpretation),
+            analysis.best.total
+        );
+    }
+
+    #[test]
+    fn test_analysis_of_largest_game() {
+        let game = large_game(25);
+        let guide = large_guide(25);
+        let analysis = game.analyze(&guide);
+        assert!(analysis.worst.total < analysis.best.total);
+        assert_eq!(
+            game.total(&guide, &analysis.worst.interpretation),
+            analysis.worst.total
+        );
+        assert!(Game::new(&["S"; 27], &[1; 27], [0, 3, 6]).is_err());
+    }
 }
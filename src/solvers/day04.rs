@@ -1,48 +1,11 @@
-use std::str::FromStr;
-
-use super::{base::AocSolver, error::InputParseError};
-
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-struct Range<T> {
-    pub lower_bound: T,
-    pub upper_bound: T,
-}
-
-impl<T: FromStr> TryFrom<&str> for Range<T> {
-    type Error = InputParseError;
-
-    fn try_from(value: &str) -> Result<Self, Self::Error> {
-        let mut parts = value.split('-');
-        Ok(Self {
-            lower_bound: parts
-                .next()
-                .ok_or_else(|| InputParseError::new("missing lower bound".into()))?
-                .parse::<T>()
-                .map_err(|_err| InputParseError::new("failed to parse lower bound".into()))?,
-            upper_bound: parts
-                .next()
-                .ok_or_else(|| InputParseError::new("missing upper bound".into()))?
-                .parse::<T>()
-                .map_err(|_err| InputParseError::new("failed to parse upper bound".into()))?,
-        })
-    }
-}
-
-impl<T: PartialOrd> Range<T> {
-    fn includes_fully(&self, other: &Self) -> bool {
-        self.lower_bound <= other.lower_bound && other.upper_bound <= self.upper_bound
-    }
-
-    fn overlaps(&self, other: &Self) -> bool {
-        (self.lower_bound <= other.lower_bound && other.lower_bound <= self.upper_bound)
-            || (self.lower_bound <= other.upper_bound && other.upper_bound <= self.upper_bound)
-            || (other.lower_bound <= self.lower_bound && self.lower_bound <= other.upper_bound)
-            || (other.lower_bound <= self.upper_bound && self.upper_bound <= other.upper_bound)
-    }
-}
+use super::{
+    base::AocSolver,
+    error::InputParseError,
+    interval::{Interval, IntervalSet},
+};
 
 pub struct Solver {
-    range_pairs: Vec<(Range<u32>, Range<u32>)>,
+    range_pairs: Vec<(Interval<u32>, Interval<u32>)>,
 }
 
 impl AocSolver<'_, usize, usize> for Solver {
@@ -59,7 +22,7 @@ impl AocSolver<'_, usize, usize> for Solver {
                     }
                     Some(Self::parse_line(line))
                 })
-                .collect::<Result<Vec<(Range<u32>, Range<u32>)>, InputParseError>>()?,
+                .collect::<Result<Vec<(Interval<u32>, Interval<u32>)>, InputParseError>>()?,
         })
     }
 
@@ -79,11 +42,72 @@ impl AocSolver<'_, usize, usize> for Solver {
                 .count(),
         ))
     }
+
+    fn visualize(&self) -> anyhow::Result<Option<String>> {
+        let covered = self.sections_covered();
+        let assigned_twice = self.sections_assigned_twice();
+        let mut output = format!(
+            "Total sections covered by all elves: {}\n\
+             Sections assigned twice within a pair: {}\n\
+             Covered ranges:",
+            covered.len(),
+            assigned_twice
+        );
+        if covered.is_empty() {
+            output.push_str(" none");
+        }
+        for interval in covered.intervals() {
+            output.push_str(&format!(" {}-{}", interval.start, interval.end));
+        }
+        output.push_str("\nUncovered gaps:");
+        for gap in covered.gaps() {
+            output.push_str(&format!(" {}-{}", gap.start, gap.end));
+        }
+        output.push('\n');
+        Ok(Some(output))
+    }
 }
 
 impl Solver {
-    fn parse_line(line: &str) -> Result<(Range<u32>, Range<u32>), InputParseError> {
-        let mut conv_iter = line.split(',').map(Range::<u32>::try_from);
+    /// Union of the sections assigned to any elf.
+    pub fn sections_covered(&self) -> IntervalSet<u32> {
+        self.range_pairs
+            .iter()
+            .fold(IntervalSet::new(), |covered, &(a, b)| {
+                covered.union(&[a, b].into_iter().collect())
+            })
+    }
+
+    /// Number of sections assigned to both elves of a pair, summed over all
+    /// pairs.
+    pub fn sections_assigned_twice(&self) -> usize {
+        self.range_pairs
+            .iter()
+            .map(|&(a, b)| {
+                let a: IntervalSet<u32> = [a].into_iter().collect();
+                a.intersection(&[b].into_iter().collect()).len()
+            })
+            .sum()
+    }
+
+    fn parse_range(value: &str) -> Result<Interval<u32>, InputParseError> {
+        let mut parts = value.split('-');
+        Ok(Interval::new(
+            parts
+                .next()
+                .ok_or_else(|| InputParseError::new("missing lower bound".into()))?
+                .parse()
+                .map_err(|_err| InputParseError::new("failed to parse lower bound".into()))?,
+            parts
+                .next()
+                .ok_or_else(|| InputParseError::new("missing upper bound".into()))?
+                .parse()
+                .map_err(|_err| InputParseError::new("failed to parse upper bound".into()))?,
+        ))
+    }
+
+    fn parse_line(line: &str) -> Result<(Interval<u32>, Interval<u32>), InputParseError> {
+        let mut conv_iter = line.split(',').map(Self::parse_range);
         Ok((
             conv_iter
                 .next()
@@ -105,4 +129,33 @@ mod tests {
         let input = include_str!("examples/day04");
         test_example_input::<Solver, _, _>(input, 2, Some(4));
     }
+
+    #[test]
+    fn test_sections_covered() {
+        let solver = Solver::new("1-3,2-4\n8-9,6-6\n11-12,13-20\n").unwrap();
+        let covered = solver.sections_covered();
+        assert_eq!(
+            covered.intervals(),
+            &[
+                Interval::new(1, 4),
+                Interval::new(6, 6),
+                Interval::new(8, 9),
+                Interval::new(11, 20)
+            ]
+        );
+        assert_eq!(covered.len(), 17);
+        assert_eq!(solver.sections_assigned_twice(), 2);
+    }
+
+    #[test]
+    fn test_visualize() {
+        let solver = Solver::new(include_str!("examples/day04")).unwrap();
+        assert_eq!(
+            solver.visualize().unwrap().unwrap(),
+            "Total sections covered by all elves: 8\n\
+             Sections assigned twice within a pair: 10\n\
+             Covered ranges: 2-9\n\
+             Uncovered gaps:\n"
+        );
+    }
 }
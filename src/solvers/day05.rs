@@ -1,9 +1,15 @@
-use std::string::FromUtf8Error;
+use std::{fmt::Display, string::FromUtf8Error};
 
 use super::{base::AocSolver, error::InputParseError};
+use lazy_static::lazy_static;
+use regex::Regex;
 
-#[derive(Clone, Copy, Debug)]
-struct Move {
+/// Crane capacities shown in the visualization in addition to the puzzle's
+/// cranes.
+const VISUALIZED_CAPACITIES: [usize; 2] = [2, 3];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Move {
     repeats: usize,
     from: usize,
     to: usize,
@@ -13,31 +19,50 @@ impl TryFrom<&str> for Move {
     type Error = InputParseError;
 
     fn try_from(line: &str) -> Result<Self, Self::Error> {
-        let mut parts = line.split_ascii_whitespace();
-        let mut next_part = || {
-            parts
-                .next()
-                .ok_or_else(|| InputParseError::new("unexpected end of line".into()))
+        lazy_static! {
+            static ref RE: Regex = Regex::new(r"^move (\d+) from (\d+) to (\d+)$").unwrap();
+        }
+        let captures = RE
+            .captures(line.trim())
+            .ok_or_else(|| InputParseError::new(format!("invalid move: {}", line)))?;
+        let stack_index = |i: usize| {
+            captures[i]
+                .parse::<usize>()?
+                .checked_sub(1)
+                .ok_or_else(|| InputParseError::new("stacks are numbered from 1".into()))
         };
-        next_part()?;
-        let repeats = str::parse(next_part()?)?;
-        next_part()?;
-        let from = str::parse::<usize>(next_part()?)? - 1;
-        next_part()?;
-        let to = str::parse::<usize>(next_part()?)? - 1;
+        Ok(Self {
+            repeats: captures[1].parse()?,
+            from: stack_index(2)?,
+            to: stack_index(3)?,
+        })
+    }
+}
 
-        Ok(Self { repeats, from, to })
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "move {} from {} to {}",
+            self.repeats,
+            self.from + 1,
+            self.to + 1
+        )
     }
 }
 
-#[derive(Clone, Debug)]
-struct Stacks(Vec<Vec<u8>>);
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Stacks(Vec<Vec<u8>>);
 
 impl TryFrom<&[&[u8]]> for Stacks {
     type Error = InputParseError;
 
+    /// Parses the drawing including the line with the stack numbers.
     fn try_from(lines: &[&[u8]]) -> Result<Self, Self::Error> {
-        let num_stacks = (lines.last().map(|&s| s.len()).unwrap_or(0) + 1) / 4;
+        let (labels, lines) = lines
+            .split_last()
+            .ok_or_else(|| InputParseError::new("missing stack drawing".into()))?;
+        let num_stacks = std::str::from_utf8(labels)?.split_whitespace().count();
         let mut stacks = Vec::with_capacity(num_stacks);
         for _ in 0..num_stacks {
             stacks.push(Vec::with_capacity(2 * lines.len()));
@@ -55,23 +80,40 @@ impl TryFrom<&[&[u8]]> for Stacks {
 }
 
 impl Stacks {
-    fn apply_move(&mut self, mv: &Move) {
-        for _ in 0..mv.repeats {
-            if let Some(item) = self.0[mv.from].pop() {
-                self.0[mv.to].push(item);
+    /// Checks that `mv` refers to existing stacks and does not move more
+    /// crates than available.
+    pub fn check_move(&self, mv: &Move) -> anyhow::Result<()> {
+        for index in [mv.from, mv.to] {
+            if index >= self.0.len() {
+                anyhow::bail!(
+                    "{}: stack {} does not exist, there are {} stacks",
+                    mv,
+                    index + 1,
+                    self.0.len()
+                );
             }
         }
+        let available = self.0[mv.from].len();
+        if available < mv.repeats {
+            anyhow::bail!(
+                "{}: stack {} holds only {} crates",
+                mv,
+                mv.from + 1,
+                available
+            );
+        }
+        Ok(())
     }
 
-    fn apply_multicrate_move(&mut self, mv: &Move) {
-        if mv.from != mv.to {
-            let from_stack = &mut self.0[mv.from];
-            let items: Vec<u8> = from_stack[from_stack.len() - mv.repeats..from_stack.len()].into();
-            for _ in 0..mv.repeats {
-                from_stack.pop();
-            }
-            self.0[mv.to].extend(items.iter());
-        }
+    /// Removes the top `count` crates from a stack, returned bottom first.
+    /// The caller must have checked the move.
+    fn lift(&mut self, from: usize, count: usize) -> Vec<u8> {
+        let stack = &mut self.0[from];
+        stack.split_off(stack.len() - count)
+    }
+
+    fn put(&mut self, to: usize, crates: Vec<u8>) {
+        self.0[to].extend(crates);
     }
 
     fn to_answer(&self) -> Result<String, FromUtf8Error> {
@@ -84,6 +126,85 @@ impl Stacks {
     }
 }
 
+/// Renders the stacks in the drawing format of the puzzle input.
+impl Display for Stacks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let height = self.0.iter().map(Vec::len).max().unwrap_or(0);
+        for level in (0..height).rev() {
+            let row: Vec<String> = self
+                .0
+                .iter()
+                .map(|stack| match stack.get(level) {
+                    Some(&item) => format!("[{}]", item as char),
+                    None => "   ".into(),
+                })
+                .collect();
+            writeln!(f, "{}", row.join(" "))?;
+        }
+        let labels: Vec<String> = (1..=self.0.len()).map(|i| format!(" {} ", i)).collect();
+        writeln!(f, "{}", labels.join(" "))
+    }
+}
+
+pub trait Crane {
+    /// Applies a move, leaving the stacks unchanged if the move is invalid.
+    fn apply(&self, stacks: &mut Stacks, mv: &Move) -> anyhow::Result<()>;
+}
+
+/// Moves one crate at a time.
+pub struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn apply(&self, stacks: &mut Stacks, mv: &Move) -> anyhow::Result<()> {
+        stacks.check_move(mv)?;
+        for _ in 0..mv.repeats {
+            let item = stacks.lift(mv.from, 1);
+            stacks.put(mv.to, item);
+        }
+        Ok(())
+    }
+}
+
+/// Moves all crates of a move at once, retaining their order.
+pub struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn apply(&self, stacks: &mut Stacks, mv: &Move) -> anyhow::Result<()> {
+        stacks.check_move(mv)?;
+        let items = stacks.lift(mv.from, mv.repeats);
+        stacks.put(mv.to, items);
+        Ok(())
+    }
+}
+
+/// Splits moves into lifts of at most `capacity` crates for another crane.
+pub struct CapacityLimited<C: Crane> {
+    crane: C,
+    capacity: usize,
+}
+
+impl<C: Crane> CapacityLimited<C> {
+    pub fn new(crane: C, capacity: usize) -> anyhow::Result<Self> {
+        if capacity == 0 {
+            anyhow::bail!("crane capacity must be positive");
+        }
+        Ok(Self { crane, capacity })
+    }
+}
+
+impl<C: Crane> Crane for CapacityLimited<C> {
+    fn apply(&self, stacks: &mut Stacks, mv: &Move) -> anyhow::Result<()> {
+        stacks.check_move(mv)?;
+        let mut remaining = mv.repeats;
+        while remaining > 0 {
+            let repeats = remaining.min(self.capacity);
+            self.crane.apply(stacks, &Move { repeats, ..*mv })?;
+            remaining -= repeats;
+        }
+        Ok(())
+    }
+}
+
 pub struct Solver {
     stacks: Stacks,
     moves: Vec<Move>,
@@ -104,25 +225,61 @@ impl AocSolver<'_, String, String> for Solver {
             .map(Move::try_from)
             .collect::<Result<Vec<Move>, _>>()?;
         Ok(Self {
-            stacks: Stacks::try_from(&stack_def[0..stack_def.len() - 1])?,
+            stacks: Stacks::try_from(&stack_def[..])?,
             moves,
         })
     }
 
     fn solve_part1(&self) -> anyhow::Result<String> {
-        let mut stacks = self.stacks.clone();
-        for mv in self.moves.iter() {
-            stacks.apply_move(mv);
-        }
-        Ok(stacks.to_answer()?)
+        Ok(self.final_state(&CrateMover9000)?.to_answer()?)
     }
 
     fn solve_part2(&self) -> anyhow::Result<Option<String>> {
+        Ok(Some(self.final_state(&CrateMover9001)?.to_answer()?))
+    }
+
+    fn visualize(&self) -> anyhow::Result<Option<String>> {
+        let states = self.replay(&CrateMover9001)?;
+        let mut output = states[0].to_string();
+        for (mv, state) in self.moves.iter().zip(&states[1..]) {
+            output.push_str(&format!("\n{}\n\n{}", mv, state));
+        }
+
+        output.push_str(&format!(
+            "\nCrateMover 9000: {}\nCrateMover 9001: {}\n",
+            self.solve_part1()?,
+            states[states.len() - 1].to_answer()?
+        ));
+        for capacity in VISUALIZED_CAPACITIES {
+            let crane = CapacityLimited::new(CrateMover9001, capacity)?;
+            output.push_str(&format!(
+                "CrateMover 9001 lifting at most {}: {}\n",
+                capacity,
+                self.final_state(&crane)?.to_answer()?
+            ));
+        }
+        Ok(Some(output))
+    }
+}
+
+impl Solver {
+    /// States of the stacks before and after each move.
+    pub fn replay(&self, crane: &dyn Crane) -> anyhow::Result<Vec<Stacks>> {
+        let mut states = vec![self.stacks.clone()];
+        for mv in &self.moves {
+            let mut stacks = states[states.len() - 1].clone();
+            crane.apply(&mut stacks, mv)?;
+            states.push(stacks);
+        }
+        Ok(states)
+    }
+
+    fn final_state(&self, crane: &dyn Crane) -> anyhow::Result<Stacks> {
         let mut stacks = self.stacks.clone();
-        for mv in self.moves.iter() {
-            stacks.apply_multicrate_move(mv);
+        for mv in &self.moves {
+            crane.apply(&mut stacks, mv)?;
         }
-        Ok(Some(stacks.to_answer()?))
+        Ok(stacks)
     }
 }
 
@@ -130,10 +287,79 @@ impl AocSolver<'_, String, String> for Solver {
 mod tests {
     use super::*;
     use crate::solvers::test::test_example_input;
+    use rstest::rstest;
 
     #[test]
     fn test_example() {
         let input = include_str!("examples/day05");
         test_example_input::<Solver, _, _>(input, "CMZ".into(), Some("MCD".into()));
     }
+
+    #[test]
+    fn test_render_round_trip() {
+        let input = include_str!("examples/day05");
+        let solver = Solver::new(input).unwrap();
+        let drawing: String = input
+            .lines()
+            .take_while(|line| !line.is_empty())
+            .map(|line| format!("{}\n", line))
+            .collect();
+        assert_eq!(solver.stacks.to_string(), drawing);
+    }
+
+    #[test]
+    fn test_replay() {
+        let solver = Solver::new(include_str!("examples/day05")).unwrap();
+        let states = solver.replay(&CrateMover9000).unwrap();
+        assert_eq!(states.len(), 5);
+        assert_eq!(
+            states[1].to_string(),
+            "[D]        \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n"
+        );
+        assert_eq!(
+            states[2].to_string(),
+            "        [Z]\n        [N]\n    [C] [D]\n    [M] [P]\n 1   2   3 \n"
+        );
+        let states = solver.replay(&CrateMover9001).unwrap();
+        assert_eq!(
+            states[2].to_string(),
+            "        [D]\n        [N]\n    [C] [Z]\n    [M] [P]\n 1   2   3 \n"
+        );
+    }
+
+    #[rstest]
+    #[case(1, "CMZ")]
+    #[case(2, "MCZ")]
+    #[case(3, "MCD")]
+    fn test_capacity_limited(#[case] capacity: usize, #[case] expected: &str) {
+        let solver = Solver::new(include_str!("examples/day05")).unwrap();
+        let crane = CapacityLimited::new(CrateMover9001, capacity).unwrap();
+        assert_eq!(
+            solver.final_state(&crane).unwrap().to_answer().unwrap(),
+            expected
+        );
+        assert!(CapacityLimited::new(CrateMover9001, 0).is_err());
+    }
+
+    #[rstest]
+    #[case("move 4 from 1 to 2")]
+    #[case("move 1 from 4 to 2")]
+    #[case("move 1 from 1 to 4")]
+    fn test_invalid_moves(#[case] mv: &str) {
+        let input = format!("[A]\n 1   2   3 \n\n{}\n", mv);
+        let solver = Solver::new(&input).unwrap();
+        let mut stacks = solver.stacks.clone();
+        for crane in [&CrateMover9000 as &dyn Crane, &CrateMover9001] {
+            assert!(crane.apply(&mut stacks, &solver.moves[0]).is_err());
+            assert_eq!(stacks, solver.stacks);
+        }
+    }
+
+    #[rstest]
+    #[case("move 1 from 0 to 2")]
+    #[case("move x from 1 to 2")]
+    #[case("move 1 from 1 into 2")]
+    fn test_invalid_move_syntax(#[case] mv: &str) {
+        assert!(Move::try_from(mv).is_err());
+    }
 }
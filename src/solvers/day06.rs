@@ -1,8 +1,51 @@
+use std::collections::VecDeque;
+
 use super::base::AocSolver;
 use anyhow::anyhow;
 
+const MAX_MARKERS_SHOWN: usize = 10;
+
+/// Detects windows of distinct bytes in a stream by keeping a count of each
+/// byte within the window.
+pub struct MarkerDetector {
+    marker_length: usize,
+    window: VecDeque<u8>,
+    counts: [usize; 256],
+    duplicates: usize,
+}
+
+impl MarkerDetector {
+    pub fn new(marker_length: usize) -> Self {
+        Self {
+            marker_length,
+            window: VecDeque::with_capacity(marker_length + 1),
+            counts: [0; 256],
+            duplicates: 0,
+        }
+    }
+
+    /// Adds the next byte and returns whether the last `marker_length` bytes
+    /// are all distinct.
+    pub fn push(&mut self, byte: u8) -> bool {
+        self.window.push_back(byte);
+        self.counts[byte as usize] += 1;
+        if self.counts[byte as usize] == 2 {
+            self.duplicates += 1;
+        }
+        if self.window.len() > self.marker_length {
+            if let Some(removed) = self.window.pop_front() {
+                self.counts[removed as usize] -= 1;
+                if self.counts[removed as usize] == 1 {
+                    self.duplicates -= 1;
+                }
+            }
+        }
+        self.window.len() == self.marker_length && self.duplicates == 0
+    }
+}
+
 pub struct Solver {
-    datastream: Vec<u32>,
+    datastream: Vec<u8>,
 }
 
 impl AocSolver<'_, usize, usize> for Solver {
@@ -11,13 +54,7 @@ impl AocSolver<'_, usize, usize> for Solver {
         Self: Sized,
     {
         Ok(Self {
-            datastream: input
-                .as_bytes()
-                .iter()
-                .copied()
-                .filter(|c| (b'a'..=b'z').contains(c))
-                .map(|c| 1u32 << (c - b'a'))
-                .collect(),
+            datastream: input.trim_end_matches(['\n', '\r']).as_bytes().into(),
         })
     }
 
@@ -28,25 +65,47 @@ impl AocSolver<'_, usize, usize> for Solver {
 
     fn solve_part2(&self) -> anyhow::Result<Option<usize>> {
         Ok(Some(self.detect_marker(14).ok_or_else(|| {
-            anyhow!("no start-of-packet marker found")
+            anyhow!("no start-of-message marker found")
         })?))
     }
+
+    fn visualize(&self) -> anyhow::Result<Option<String>> {
+        let mut output = String::new();
+        for marker_length in [4, 14] {
+            let markers: Vec<usize> = self.markers(marker_length).collect();
+            let shown: Vec<String> = markers
+                .iter()
+                .take(MAX_MARKERS_SHOWN)
+                .map(usize::to_string)
+                .collect();
+            output.push_str(&format!(
+                "{} markers of length {}: {}{}\n",
+                markers.len(),
+                marker_length,
+                shown.join(", "),
+                if markers.len() > MAX_MARKERS_SHOWN {
+                    ", ..."
+                } else {
+                    ""
+                }
+            ));
+        }
+        Ok(Some(output))
+    }
 }
 
 impl Solver {
+    /// Number of bytes processed at the end of each marker.
+    fn markers(&self, marker_length: usize) -> impl Iterator<Item = usize> + '_ {
+        let mut detector = MarkerDetector::new(marker_length);
+        self.datastream
+            .iter()
+            .enumerate()
+            .filter_map(move |(i, &byte)| detector.push(byte).then_some(i + 1))
+    }
+
     fn detect_marker(&self, marker_length: usize) -> Option<usize> {
-        for i in marker_length - 1..self.datastream.len() {
-            let unique_char_count = self.datastream[i + 1 - marker_length..=i]
-                .iter()
-                .copied()
-                .reduce(|accum, item| accum | item)
-                .expect("no item in window")
-                .count_ones();
-            if unique_char_count as usize == marker_length {
-                return Some(i + 1);
-            }
-        }
-        None
+        self.markers(marker_length).next()
     }
 }
 
@@ -76,6 +135,26 @@ mod tests {
             Some(answer)
         );
     }
+
+    #[rstest]
+    #[case("abcabc\n", 3, &[3, 4, 5, 6])]
+    #[case("aabc\n", 3, &[4])]
+    #[case("aAbB", 4, &[4])]
+    #[case("a1!a b\tc", 3, &[3, 4, 5, 6, 7, 8])]
+    #[case("\u{e4}\u{e4}", 2, &[2, 3, 4])]
+    #[case("aaaa", 2, &[])]
+    #[case("ab", 3, &[])]
+    fn test_all_markers(
+        #[case] input: &str,
+        #[case] marker_length: usize,
+        #[case] expected: &[usize],
+    ) {
+        let solver = Solver::new(input).unwrap();
+        assert_eq!(
+            solver.markers(marker_length).collect::<Vec<usize>>(),
+            expected
+        );
+    }
 }
 
 #[cfg(all(feature = "bench", test))]
@@ -1,19 +1,46 @@
+use std::fmt::Display;
+
 use super::{base::AocSolver, error::InputParseError};
 
-struct File {
+const DISK_SIZE: usize = 70_000_000;
+const REQUIRED_FREE: usize = 30_000_000;
+
+pub struct File {
+    name: String,
     size: usize,
 }
 
-struct Dir {
+pub struct Dir {
     name: String,
     size: usize,
     directories: Vec<Dir>,
     files: Vec<File>,
 }
 
+pub enum Entry<'a> {
+    Dir(&'a Dir),
+    File(&'a File),
+}
+
+impl<'a> Entry<'a> {
+    pub fn name(&self) -> &'a str {
+        match self {
+            Entry::Dir(dir) => &dir.name,
+            Entry::File(file) => &file.name,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        match self {
+            Entry::Dir(dir) => dir.size,
+            Entry::File(file) => file.size,
+        }
+    }
+}
+
 impl File {
-    pub fn new(size: usize) -> Self {
-        Self { size }
+    pub fn new(name: String, size: usize) -> Self {
+        Self { name, size }
     }
 }
 
@@ -26,6 +53,38 @@ impl Dir {
             files: vec![],
         }
     }
+
+    /// Lists the directory contents sorted by name.
+    pub fn entries(&self) -> Vec<Entry<'_>> {
+        let mut entries: Vec<Entry> = self
+            .directories
+            .iter()
+            .map(Entry::Dir)
+            .chain(self.files.iter().map(Entry::File))
+            .collect();
+        entries.sort_by_key(|entry| entry.name());
+        entries
+    }
+
+    fn fmt_tree(&self, f: &mut std::fmt::Formatter<'_>, depth: usize) -> std::fmt::Result {
+        for entry in self.entries() {
+            match entry {
+                Entry::Dir(dir) => {
+                    writeln!(f, "{:indent$}- {} (dir)", "", dir.name, indent = 2 * depth)?;
+                    dir.fmt_tree(f, depth + 1)?;
+                }
+                Entry::File(file) => writeln!(
+                    f,
+                    "{:indent$}- {} (file, size={})",
+                    "",
+                    file.name,
+                    file.size,
+                    indent = 2 * depth
+                )?,
+            }
+        }
+        Ok(())
+    }
 }
 
 struct WorkingDir<'a> {
@@ -54,11 +113,16 @@ impl<'a> WorkingDir<'a> {
         pointer
     }
 
-    pub fn cd(&mut self, name: &str) {
-        if name == "/" {
+    pub fn cd(&mut self, path: &str) {
+        if path.starts_with('/') {
             self.path.clear();
-            return;
         }
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            self.cd_single(name);
+        }
+    }
+
+    fn cd_single(&mut self, name: &str) {
         if name == ".." {
             self.path.pop();
             return;
@@ -75,7 +139,26 @@ impl<'a> WorkingDir<'a> {
         self.current_mut().directories.push(Dir::new(name.into()));
     }
 
-    pub fn add_file(&mut self, file: File) {
+    /// Adds a file to the current directory. Listing the same file again is
+    /// ignored, but a listing conflicting with a known entry is an error.
+    pub fn add_file(&mut self, file: File) -> Result<(), InputParseError> {
+        let current = self.current();
+        if let Some(existing) = current.files.iter().find(|f| f.name == file.name) {
+            if existing.size == file.size {
+                return Ok(());
+            }
+            return Err(InputParseError::new(format!(
+                "conflicting sizes for file {}",
+                file.name
+            )));
+        }
+        if current.directories.iter().any(|dir| dir.name == file.name) {
+            return Err(InputParseError::new(format!(
+                "{} listed as both file and directory",
+                file.name
+            )));
+        }
+
         let mut pointer: &mut Dir = self.root;
         for index in &self.path {
             pointer.size += file.size;
@@ -83,10 +166,17 @@ impl<'a> WorkingDir<'a> {
         }
         pointer.size += file.size;
         pointer.files.push(file);
+        Ok(())
     }
 
-    pub fn add_dir(&mut self, new_dir: Dir) {
+    pub fn add_dir(&mut self, new_dir: Dir) -> Result<(), InputParseError> {
         let current = self.current_mut();
+        if current.files.iter().any(|file| file.name == new_dir.name) {
+            return Err(InputParseError::new(format!(
+                "{} listed as both file and directory",
+                new_dir.name
+            )));
+        }
         let dirs = &mut current.directories;
         if !dirs
             .iter()
@@ -94,6 +184,88 @@ impl<'a> WorkingDir<'a> {
         {
             dirs.push(new_dir);
         }
+        Ok(())
+    }
+}
+
+/// In-memory filesystem reconstructed from a terminal transcript.
+pub struct FileSystem {
+    root: Dir,
+}
+
+impl FileSystem {
+    pub fn from_transcript(input: &str) -> anyhow::Result<Self> {
+        let mut root = Dir::new("/".into());
+        let mut working_dir = WorkingDir::new(&mut root);
+        for line in input.lines() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            } else if let Some(name) = line.strip_prefix("$ cd ") {
+                working_dir.cd(name.trim());
+            } else if line.starts_with("$ ls") {
+                // no op
+            } else if let Some(name) = line.strip_prefix("dir ") {
+                working_dir.add_dir(Dir::new(name.trim().into()))?;
+            } else {
+                let (size, name) = line
+                    .split_once(' ')
+                    .ok_or_else(|| InputParseError::new("invalid file listing".into()))?;
+                working_dir.add_file(File::new(name.trim().into(), size.parse()?))?;
+            }
+        }
+        Ok(Self { root })
+    }
+
+    pub fn root(&self) -> &Dir {
+        &self.root
+    }
+
+    /// Resolves an absolute path like `/a/e`.
+    pub fn lookup(&self, path: &str) -> Option<Entry<'_>> {
+        let mut entry = Entry::Dir(&self.root);
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            let Entry::Dir(dir) = entry else {
+                return None;
+            };
+            entry = dir
+                .entries()
+                .into_iter()
+                .find(|entry| entry.name() == name)?;
+        }
+        Some(entry)
+    }
+
+    pub fn size(&self, path: &str) -> Option<usize> {
+        self.lookup(path).map(|entry| entry.size())
+    }
+
+    /// Total size of every directory below `path` (inclusive), largest first.
+    pub fn du(&self, path: &str) -> Option<Vec<(String, usize)>> {
+        fn collect(dir: &Dir, path: String, report: &mut Vec<(String, usize)>) {
+            for subdir in &dir.directories {
+                collect(subdir, format!("{}{}/", path, subdir.name), report);
+            }
+            report.push((path, dir.size));
+        }
+
+        let Entry::Dir(dir) = self.lookup(path)? else {
+            return None;
+        };
+        let mut prefix: String = path.trim_end_matches('/').into();
+        prefix.push('/');
+        let mut report = vec![];
+        collect(dir, prefix, &mut report);
+        report.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Some(report)
+    }
+}
+
+/// Renders the filesystem as a tree in the style of the puzzle description.
+impl Display for FileSystem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "- / (dir)")?;
+        self.root.fmt_tree(f, 1)
     }
 }
 
@@ -125,8 +297,8 @@ struct FindDirectoryToDeleteVisitor {
 
 impl FindDirectoryToDeleteVisitor {
     pub fn visit(root: &Dir) -> usize {
-        let available = 70_000_000 - root.size;
-        let to_free = 30_000_000 - available;
+        let available = DISK_SIZE - root.size;
+        let to_free = REQUIRED_FREE.saturating_sub(available);
         let mut visitor = Self {
             deletion_size: root.size,
             to_free,
@@ -145,8 +317,293 @@ impl FindDirectoryToDeleteVisitor {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CleanupStrategy {
+    FewestDeletions,
+    LeastBytes,
+}
+
+impl CleanupStrategy {
+    /// Ordering key of a plan, smaller is better.
+    fn key(&self, deletions: usize, freed: usize) -> (usize, usize) {
+        match self {
+            CleanupStrategy::FewestDeletions => (deletions, freed),
+            CleanupStrategy::LeastBytes => (freed, deletions),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CleanupPlan {
+    pub to_free: usize,
+    pub freed: usize,
+    pub deletions: Vec<(String, usize)>,
+}
+
+impl Display for CleanupPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Delete {} director{} freeing {} of {} bytes required:",
+            self.deletions.len(),
+            if self.deletions.len() == 1 {
+                "y"
+            } else {
+                "ies"
+            },
+            self.freed,
+            self.to_free
+        )?;
+        for (path, size) in &self.deletions {
+            writeln!(f, "{}\t{}", size, path)?;
+        }
+        Ok(())
+    }
+}
+
+/// Directory in pre-order. All directories within its subtree precede
+/// `subtree_end`.
+struct Candidate {
+    path: String,
+    size: usize,
+    deletable: bool,
+    subtree_end: usize,
+}
+
+/// Finds a set of directories to delete to get the required free space.
+/// Nested directories are never chosen together, so no size is counted twice.
+///
+/// The planner searches the directories with branch and bound. Besides the
+/// search stack, it keeps tables quadratic in the number of directories.
+/// Freeing the least bytes is a subset sum problem, so the search may still
+/// take exponential time on adversarial inputs.
+pub struct CleanupPlanner<'a> {
+    fs: &'a FileSystem,
+    disk_size: usize,
+    required_free: usize,
+    keep: Vec<String>,
+}
+
+impl<'a> CleanupPlanner<'a> {
+    /// Directories containing any of the `keep` paths, or contained in them,
+    /// are never deleted.
+    pub fn new(fs: &'a FileSystem, disk_size: usize, required_free: usize, keep: &[&str]) -> Self {
+        Self {
+            fs,
+            disk_size,
+            required_free,
+            keep: keep.iter().map(|path| path.to_string()).collect(),
+        }
+    }
+
+    /// Plans for several strategies at once, sharing the bounds.
+    pub fn plans(&self, strategies: &[CleanupStrategy]) -> anyhow::Result<Vec<CleanupPlan>> {
+        let used = self.fs.root().size;
+        if used > self.disk_size {
+            anyhow::bail!("{} bytes used exceed disk size {}", used, self.disk_size);
+        }
+        let to_free = self.required_free.saturating_sub(self.disk_size - used);
+
+        let mut kept = Vec::with_capacity(self.keep.len());
+        for path in &self.keep {
+            let entry = self
+                .fs
+                .lookup(path)
+                .ok_or_else(|| anyhow::anyhow!("path {} to keep does not exist", path))?;
+            let mut path = path.trim_end_matches('/').to_string();
+            if let Entry::Dir(_) = entry {
+                path.push('/');
+            }
+            kept.push(path);
+        }
+
+        let mut candidates = vec![];
+        Self::collect_candidates(self.fs.root(), "/".into(), &kept, &mut candidates);
+        let max_freeable = Self::max_freeable(&candidates);
+        let by_size = Self::by_size(&candidates);
+
+        strategies
+            .iter()
+            .map(|&strategy| {
+                let mut search = PlanSearch {
+                    strategy,
+                    to_free,
+                    candidates: &candidates,
+                    max_freeable: &max_freeable,
+                    by_size: &by_size,
+                    max_deletions: 0,
+                    capped: false,
+                    chosen: vec![],
+                    best: None,
+                };
+                search.deepen();
+                let (_, chosen) = search.best.ok_or_else(|| {
+                    anyhow::anyhow!("cannot free {} bytes without deleting kept paths", to_free)
+                })?;
+                let deletions: Vec<(String, usize)> = chosen
+                    .into_iter()
+                    .map(|i| (candidates[i].path.clone(), candidates[i].size))
+                    .collect();
+                Ok(CleanupPlan {
+                    to_free,
+                    freed: deletions.iter().map(|(_, size)| size).sum(),
+                    deletions,
+                })
+            })
+            .collect()
+    }
+
+    fn collect_candidates(
+        dir: &Dir,
+        path: String,
+        kept: &[String],
+        candidates: &mut Vec<Candidate>,
+    ) {
+        let index = candidates.len();
+        candidates.push(Candidate {
+            deletable: !kept
+                .iter()
+                .any(|kept| kept.starts_with(&path) || path.starts_with(kept.as_str())),
+            path: path.clone(),
+            size: dir.size,
+            subtree_end: 0,
+        });
+        let mut subdirs: Vec<&Dir> = dir.directories.iter().collect();
+        subdirs.sort_by_key(|subdir| std::cmp::Reverse(subdir.size));
+        for subdir in subdirs {
+            Self::collect_candidates(
+                subdir,
+                format!("{}{}/", path, subdir.name),
+                kept,
+                candidates,
+            );
+        }
+        candidates[index].subtree_end = candidates.len();
+    }
+
+    /// Maximum number of bytes that can be freed from each candidate onwards
+    /// with at most as many deletions as the position in the inner list.
+    /// Entries beyond the end of a list equal its last entry.
+    fn max_freeable(candidates: &[Candidate]) -> Vec<Vec<usize>> {
+        let mut max_freeable = vec![vec![0]; candidates.len() + 1];
+        for (i, candidate) in candidates.iter().enumerate().rev() {
+            let skipped = &max_freeable[i + 1];
+            let mut freeable = skipped.clone();
+            if candidate.deletable {
+                let taken = &max_freeable[candidate.subtree_end];
+                freeable.resize(skipped.len().max(taken.len() + 1), *skipped.last().unwrap());
+                for (deletions, max) in freeable.iter_mut().enumerate().skip(1) {
+                    let rest = taken[(deletions - 1).min(taken.len() - 1)];
+                    *max = (*max).max(candidate.size + rest);
+                }
+            }
+            max_freeable[i] = freeable;
+        }
+        max_freeable
+    }
+
+    /// Deletable candidates from each candidate onwards as pairs of size and
+    /// index, ordered by size.
+    fn by_size(candidates: &[Candidate]) -> Vec<Vec<(usize, usize)>> {
+        let mut by_size = vec![vec![]; candidates.len() + 1];
+        for (i, candidate) in candidates.iter().enumerate().rev() {
+            let mut deletable = by_size[i + 1].clone();
+            if candidate.deletable {
+                let position = deletable.partition_point(|&(size, _)| size < candidate.size);
+                deletable.insert(position, (candidate.size, i));
+            }
+            by_size[i] = deletable;
+        }
+        by_size
+    }
+}
+
+/// Depth-first search over the candidates in pre-order, where each
+/// candidate is either deleted, skipping its subtree, or kept.
+struct PlanSearch<'a> {
+    strategy: CleanupStrategy,
+    to_free: usize,
+    candidates: &'a [Candidate],
+    max_freeable: &'a [Vec<usize>],
+    by_size: &'a [Vec<(usize, usize)>],
+    max_deletions: usize,
+    /// Whether `max_deletions` cut off any branch of the last search.
+    capped: bool,
+    chosen: Vec<usize>,
+    best: Option<((usize, usize), Vec<usize>)>,
+}
+
+impl PlanSearch<'_> {
+    /// Repeats the search allowing one more deletion each time, until more
+    /// deletions cannot give a better plan. Plans with few deletions are
+    /// found first, which keeps the bounds tight.
+    fn deepen(&mut self) {
+        loop {
+            self.explore(0, 0);
+            let bound = self.strategy.key(self.max_deletions + 1, self.to_free);
+            if !self.capped || self.best.as_ref().is_some_and(|(best, _)| bound >= *best) {
+                break;
+            }
+            self.capped = false;
+            self.max_deletions += 1;
+        }
+    }
+
+    fn explore(&mut self, index: usize, freed: usize) {
+        if freed >= self.to_free {
+            // Choosing more directories only makes a plan worse.
+            let key = self.strategy.key(self.chosen.len(), freed);
+            if self.best.as_ref().is_none_or(|(best, _)| key < *best) {
+                self.best = Some((key, self.chosen.clone()));
+            }
+            return;
+        }
+
+        let Some(more_deletions) = self.max_freeable[index]
+            .iter()
+            .position(|&max| freed + max >= self.to_free)
+        else {
+            return;
+        };
+        if self.chosen.len() + more_deletions > self.max_deletions {
+            self.capped = true;
+            return;
+        }
+        let bound = self
+            .strategy
+            .key(self.chosen.len() + more_deletions, self.to_free);
+        if self.best.as_ref().is_some_and(|(best, _)| bound >= *best) {
+            return;
+        }
+
+        if self.chosen.len() + 1 == self.max_deletions {
+            // None of the remaining candidates is nested in a chosen one, so
+            // the smallest one freeing enough completes the best plan.
+            let by_size = &self.by_size[index];
+            self.capped |= by_size.len() > 1;
+            let missing = self.to_free - freed;
+            if let Some(&(size, last)) =
+                by_size.get(by_size.partition_point(|&(size, _)| size < missing))
+            {
+                self.chosen.push(last);
+                self.explore(self.candidates.len(), freed + size);
+                self.chosen.pop();
+            }
+            return;
+        }
+
+        let candidate = &self.candidates[index];
+        if candidate.deletable {
+            self.chosen.push(index);
+            self.explore(candidate.subtree_end, freed + candidate.size);
+            self.chosen.pop();
+        }
+        self.explore(index + 1, freed);
+    }
+}
+
 pub struct Solver {
-    root: Dir,
+    fs: FileSystem,
 }
 
 impl AocSolver<'_, usize, usize> for Solver {
@@ -154,34 +611,38 @@ impl AocSolver<'_, usize, usize> for Solver {
     where
         Self: Sized,
     {
-        let mut root = Dir::new("".into());
-        let mut working_dir = WorkingDir::new(&mut root);
-        for line in input.lines() {
-            let line = line.trim();
-            if line.is_empty() {
-                continue;
-            } else if let Some(name) = line.strip_prefix("$ cd ") {
-                working_dir.cd(name.trim());
-            } else if line.starts_with("$ ls") {
-                // no op
-            } else if let Some(name) = line.strip_prefix("dir ") {
-                working_dir.add_dir(Dir::new(name.trim().into()));
-            } else {
-                let split_idx = line
-                    .find(' ')
-                    .ok_or_else(|| InputParseError::new("invalid file listing".into()))?;
-                working_dir.add_file(File::new(line[..split_idx].parse()?))
-            }
-        }
-        Ok(Self { root })
+        Ok(Self {
+            fs: FileSystem::from_transcript(input)?,
+        })
     }
 
     fn solve_part1(&self) -> anyhow::Result<usize> {
-        Ok(TotalSizeOfDeletionCandidatesVisitor::visit(&self.root))
+        Ok(TotalSizeOfDeletionCandidatesVisitor::visit(self.fs.root()))
     }
 
     fn solve_part2(&self) -> anyhow::Result<Option<usize>> {
-        Ok(Some(FindDirectoryToDeleteVisitor::visit(&self.root)))
+        Ok(Some(FindDirectoryToDeleteVisitor::visit(self.fs.root())))
+    }
+
+    fn visualize(&self) -> anyhow::Result<Option<String>> {
+        let mut output = self.fs.to_string();
+        output.push_str(&format!(
+            "\nTotal used: {}\n",
+            self.fs.size("/").unwrap_or_default()
+        ));
+        for (path, size) in self.fs.du("/").unwrap_or_default() {
+            output.push_str(&format!("{}\t{}\n", size, path));
+        }
+        let strategies = [
+            CleanupStrategy::FewestDeletions,
+            CleanupStrategy::LeastBytes,
+        ];
+        let plans =
+            CleanupPlanner::new(&self.fs, DISK_SIZE, REQUIRED_FREE, &[]).plans(&strategies)?;
+        for (strategy, plan) in strategies.iter().zip(plans) {
+            output.push_str(&format!("\n{:?}: {}", strategy, plan));
+        }
+        Ok(Some(output))
     }
 }
 
@@ -189,10 +650,226 @@ impl AocSolver<'_, usize, usize> for Solver {
 mod tests {
     use super::*;
     use crate::solvers::test::test_example_input;
+    use rstest::rstest;
 
     #[test]
     fn test_example() {
         let input = include_str!("examples/day07");
         test_example_input::<Solver, _, _>(input, 95437, Some(24933642));
     }
+
+    #[test]
+    fn test_lookup() {
+        let fs = FileSystem::from_transcript(include_str!("examples/day07")).unwrap();
+        assert_eq!(fs.size("/a/e"), Some(584));
+        assert_eq!(fs.size("/a"), Some(94853));
+        assert_eq!(fs.size("/d/d.log"), Some(8033020));
+        assert_eq!(fs.size("/"), Some(48381165));
+        assert_eq!(fs.size("/a/x"), None);
+        assert_eq!(fs.size("/b.txt/x"), None);
+        let Some(Entry::Dir(dir)) = fs.lookup("/a") else {
+            panic!("/a is not a directory");
+        };
+        let names: Vec<&str> = dir.entries().iter().map(|entry| entry.name()).collect();
+        assert_eq!(names, vec!["e", "f", "g", "h.lst"]);
+    }
+
+    #[test]
+    fn test_du() {
+        let fs = FileSystem::from_transcript(include_str!("examples/day07")).unwrap();
+        assert_eq!(fs.du("/a/e").unwrap(), vec![("/a/e/".into(), 584)]);
+        assert!(fs.du("/b.txt").is_none());
+        assert_eq!(
+            fs.du("/").unwrap(),
+            vec![
+                ("/".into(), 48381165),
+                ("/d/".into(), 24933642),
+                ("/a/".into(), 94853),
+                ("/a/e/".into(), 584),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tree() {
+        let fs = FileSystem::from_transcript(include_str!("examples/day07")).unwrap();
+        assert_eq!(
+            fs.to_string(),
+            "- / (dir)\n\
+             \x20 - a (dir)\n\
+             \x20   - e (dir)\n\
+             \x20     - i (file, size=584)\n\
+             \x20   - f (file, size=29116)\n\
+             \x20   - g (file, size=2557)\n\
+             \x20   - h.lst (file, size=62596)\n\
+             \x20 - b.txt (file, size=14848514)\n\
+             \x20 - c.dat (file, size=8504156)\n\
+             \x20 - d (dir)\n\
+             \x20   - d.ext (file, size=5626152)\n\
+             \x20   - d.log (file, size=8033020)\n\
+             \x20   - j (file, size=4060174)\n\
+             \x20   - k (file, size=7214296)\n"
+        );
+    }
+
+    #[test]
+    fn test_repeated_listing() {
+        let input = "$ cd /\n$ ls\n10 a\ndir b\n$ ls\n10 a\ndir b\n";
+        let fs = FileSystem::from_transcript(input).unwrap();
+        assert_eq!(fs.size("/"), Some(10));
+    }
+
+    #[test]
+    fn test_conflicting_listing() {
+        assert!(FileSystem::from_transcript("$ cd /\n$ ls\n10 a\n$ ls\n20 a\n").is_err());
+        assert!(FileSystem::from_transcript("$ cd /\n$ ls\n10 a\n$ ls\ndir a\n").is_err());
+    }
+
+    const CLEANUP_EXAMPLE: &str = "$ cd /
+$ ls
+dir x
+dir y
+dir z
+$ cd x
+$ ls
+dir w
+50 x.bin
+$ cd w
+$ ls
+50 w.bin
+$ cd /y
+$ ls
+40 y.bin
+$ cd /z
+$ ls
+35 z.bin
+";
+
+    #[rstest]
+    #[case(CleanupStrategy::FewestDeletions, &[], &["/x/"])]
+    #[case(CleanupStrategy::LeastBytes, &[], &["/y/", "/z/"])]
+    #[case(CleanupStrategy::FewestDeletions, &["/y"], &["/x/"])]
+    #[case(CleanupStrategy::LeastBytes, &["/y"], &["/x/w/", "/z/"])]
+    #[case(CleanupStrategy::FewestDeletions, &["/x/w/w.bin"], &["/y/", "/z/"])]
+    fn test_cleanup_plan(
+        #[case] strategy: CleanupStrategy,
+        #[case] keep: &[&str],
+        #[case] expected: &[&str],
+    ) {
+        let fs = FileSystem::from_transcript(CLEANUP_EXAMPLE).unwrap();
+        let plan = CleanupPlanner::new(&fs, 200, 95, keep)
+            .plans(&[strategy])
+            .unwrap()
+            .remove(0);
+        let paths: Vec<&str> = plan
+            .deletions
+            .iter()
+            .map(|(path, _)| path.as_str())
+            .collect();
+        assert_eq!(paths, expected);
+        assert_eq!(plan.to_free, 70);
+        assert_eq!(
+            plan.freed,
+            plan.deletions.iter().map(|(_, size)| size).sum::<usize>()
+        );
+    }
+
+    #[test]
+    fn test_cleanup_plan_matches_part2() {
+        let fs = FileSystem::from_transcript(include_str!("examples/day07")).unwrap();
+        let plan = CleanupPlanner::new(&fs, DISK_SIZE, REQUIRED_FREE, &[])
+            .plans(&[CleanupStrategy::LeastBytes])
+            .unwrap()
+            .remove(0);
+        assert_eq!(plan.deletions, vec![("/d/".into(), 24933642)]);
+    }
+
+    #[test]
+    fn test_cleanup_plan_errors() {
+        let fs = FileSystem::from_transcript(CLEANUP_EXAMPLE).unwrap();
+        assert!(CleanupPlanner::new(&fs, 200, 95, &["/missing"])
+            .plans(&[CleanupStrategy::LeastBytes])
+            .is_err());
+        assert!(CleanupPlanner::new(&fs, 200, 95, &["/x", "/y"])
+            .plans(&[CleanupStrategy::LeastBytes])
+            .is_err());
+        assert!(CleanupPlanner::new(&fs, 100, 0, &[])
+            .plans(&[CleanupStrategy::LeastBytes])
+            .is_err());
+        let plan = CleanupPlanner::new(&fs, 1000, 95, &[])
+            .plans(&[CleanupStrategy::FewestDeletions])
+            .unwrap()
+            .remove(0);
+        assert!(plan.deletions.is_empty());
+    }
+
+    /// Transcript of a pseudo-random tree with `num_dirs` directories, each
+    /// holding a single file.
+    fn generated_transcript(num_dirs: usize, max_file_size: usize, seed: u64) -> String {
+        let mut state = seed;
+        let mut random = |bound: usize| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) as usize % bound
+        };
+        let mut paths = vec![String::from("/")];
+        let mut transcript = String::new();
+        for i in 1..num_dirs {
+            let parent = paths[random(i)].clone();
+            transcript.push_str(&format!("$ cd {}\n$ ls\ndir d{}\n", parent, i));
+            paths.push(format!("{}d{}/", parent, i));
+        }
+        for path in paths {
+            let size = 1 + random(max_file_size);
+            transcript.push_str(&format!("$ cd {}\n$ ls\n{} f\n", path, size));
+        }
+        transcript
+    }
+
+    #[rstest]
+    #[case(CleanupStrategy::FewestDeletions)]
+    #[case(CleanupStrategy::LeastBytes)]
+    fn test_cleanup_plan_is_optimal(#[case] strategy: CleanupStrategy) {
+        for seed in 0..20 {
+            let fs = FileSystem::from_transcript(&generated_transcript(10, 100, seed)).unwrap();
+            let used = fs.size("/").unwrap();
+            let planner = CleanupPlanner::new(&fs, used, used / 2, &[]);
+            let plan = planner.plans(&[strategy]).unwrap().remove(0);
+
+            let dirs: Vec<(String, usize)> = fs.du("/").unwrap();
+            let best = (0..1u32 << dirs.len())
+                .filter_map(|subset| {
+                    let chosen: Vec<&(String, usize)> = (0..dirs.len())
+                        .filter(|&i| subset & (1 << i) != 0)
+                        .map(|i| &dirs[i])
+                        .collect();
+                    let nested = chosen.iter().any(|(a, _)| {
+                        chosen
+                            .iter()
+                            .any(|(b, _)| a != b && b.starts_with(a.as_str()))
+                    });
+                    let freed: usize = chosen.iter().map(|(_, size)| size).sum();
+                    (!nested && freed >= plan.to_free).then(|| strategy.key(chosen.len(), freed))
+                })
+                .min();
+            assert_eq!(Some(strategy.key(plan.deletions.len(), plan.freed)), best);
+        }
+    }
+
+    #[test]
+    fn test_cleanup_plan_scales() {
+        let fs = FileSystem::from_transcript(&generated_transcript(190, 300_000, 42)).unwrap();
+        let used = fs.size("/").unwrap();
+        let start = std::time::Instant::now();
+        let plans = CleanupPlanner::new(&fs, used, 8_000_000, &[])
+            .plans(&[
+                CleanupStrategy::FewestDeletions,
+                CleanupStrategy::LeastBytes,
+            ])
+            .unwrap();
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+        assert_eq!(plans[0].deletions.len(), 1);
+        assert_eq!(plans[1].freed, plans[1].to_free);
+    }
 }
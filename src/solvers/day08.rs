@@ -91,7 +91,7 @@ impl<'a> AocSolver<'a, usize, usize> for Solver<'a> {
 
 impl<'a> Solver<'a> {
     fn height(&self) -> usize {
-        (self.input.len() + self.width - 1) / self.width
+        self.input.len().div_ceil(self.width)
     }
 
     fn evaluate_tree_house_spot(&self, pos: (usize, usize)) -> usize {
@@ -1,39 +1,120 @@
+use std::collections::BTreeSet;
+
 use super::{base::AocSolver, error::InputParseError};
 
 type Pos = (i32, i32);
 
-struct Rope<const LENGTH: usize> {
-    segments: [Pos; LENGTH],
+/// Knots in the order they follow each other, head first.
+struct Rope {
+    knots: Vec<Pos>,
 }
 
-impl<const LENGTH: usize> Rope<LENGTH> {
-    pub fn new() -> Self {
+impl Rope {
+    pub fn new(length: usize) -> Self {
         Self {
-            segments: [(0, 0); LENGTH],
+            knots: vec![(0, 0); length],
         }
     }
 
-    pub fn tail(&self) -> Pos {
-        *self.segments.last().unwrap()
-    }
-
     pub fn move_rope(&mut self, delta: Pos) {
-        self.segments[0] = (self.segments[0].0 + delta.0, self.segments[0].1 + delta.1);
-        for i in 1..LENGTH {
+        self.knots[0] = (self.knots[0].0 + delta.0, self.knots[0].1 + delta.1);
+        for i in 1..self.knots.len() {
             let stretch: Pos = (
-                self.segments[i - 1].0 - self.segments[i].0,
-                self.segments[i - 1].1 - self.segments[i].1,
+                self.knots[i - 1].0 - self.knots[i].0,
+                self.knots[i - 1].1 - self.knots[i].1,
             );
             if stretch.0.abs() >= 2 || stretch.1.abs() >= 2 {
-                self.segments[i] = (
-                    self.segments[i].0 + stretch.0.signum(),
-                    self.segments[i].1 + stretch.1.signum(),
+                self.knots[i] = (
+                    self.knots[i].0 + stretch.0.signum(),
+                    self.knots[i].1 + stretch.1.signum(),
                 );
             }
         }
     }
 }
 
+/// Rope together with the positions visited by each of its knots.
+pub struct RopeSimulation {
+    rope: Rope,
+    visited: Vec<BTreeSet<Pos>>,
+}
+
+impl RopeSimulation {
+    pub fn new(length: usize) -> anyhow::Result<Self> {
+        if length == 0 {
+            anyhow::bail!("a rope needs at least one knot");
+        }
+        Ok(Self {
+            rope: Rope::new(length),
+            visited: vec![BTreeSet::from([(0, 0)]); length],
+        })
+    }
+
+    pub fn move_rope(&mut self, delta: Pos) {
+        self.rope.move_rope(delta);
+        for (visited, &knot) in self.visited.iter_mut().zip(&self.rope.knots) {
+            visited.insert(knot);
+        }
+    }
+
+    pub fn visited(&self, knot: usize) -> Option<&BTreeSet<Pos>> {
+        self.visited.get(knot)
+    }
+
+    pub fn tail_visited(&self) -> &BTreeSet<Pos> {
+        &self.visited[self.visited.len() - 1]
+    }
+
+    fn bounds(&self) -> (Pos, Pos) {
+        self.visited
+            .iter()
+            .flatten()
+            .fold(((0, 0), (0, 0)), |(min, max), &(x, y)| {
+                ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y)))
+            })
+    }
+
+    fn render(&self, symbol: impl Fn(Pos) -> char) -> String {
+        let (min, max) = self.bounds();
+        let mut output = String::new();
+        for y in (min.1..=max.1).rev() {
+            output.extend((min.0..=max.0).map(|x| symbol((x, y))));
+            output.push('\n');
+        }
+        output
+    }
+
+    /// Renders the knots as in the puzzle description: `H` for the head,
+    /// `T` for the tail of a two knot rope and otherwise the knot's index.
+    /// Knots beyond the ninth are drawn as `*`. Knots earlier in the rope
+    /// cover later ones.
+    pub fn render_rope(&self) -> String {
+        let knots = &self.rope.knots;
+        self.render(|pos| match knots.iter().position(|&knot| knot == pos) {
+            Some(0) => 'H',
+            Some(1) if knots.len() == 2 => 'T',
+            Some(i) => char::from_digit(i as u32, 10).unwrap_or('*'),
+            None if pos == (0, 0) => 's',
+            None => '.',
+        })
+    }
+
+    /// Renders the positions visited by a knot as `#` with `s` marking the
+    /// start.
+    pub fn render_trail(&self, knot: usize) -> Option<String> {
+        let visited = self.visited(knot)?;
+        Some(self.render(|pos| {
+            if pos == (0, 0) {
+                's'
+            } else if visited.contains(&pos) {
+                '#'
+            } else {
+                '.'
+            }
+        }))
+    }
+}
+
 fn parse_direction(direction: u8) -> Result<Pos, InputParseError> {
     match direction {
         b'R' => Ok((1, 0)),
@@ -45,8 +126,7 @@ fn parse_direction(direction: u8) -> Result<Pos, InputParseError> {
 }
 
 pub struct Solver {
-    tail_positions_part1: std::collections::BTreeSet<Pos>,
-    tail_positions_part2: std::collections::BTreeSet<Pos>,
+    moves: Vec<(Pos, usize)>,
 }
 
 impl AocSolver<'_, usize, usize> for Solver {
@@ -54,12 +134,8 @@ impl AocSolver<'_, usize, usize> for Solver {
     where
         Self: Sized,
     {
-        let mut rope_part1 = Rope::<2>::new();
-        let mut rope_part2 = Rope::<10>::new();
-        let mut tail_positions_part1 = std::collections::BTreeSet::new();
-        let mut tail_positions_part2 = std::collections::BTreeSet::new();
-
-        for line in input.lines() {
+        let mut moves = vec![];
+        for line in input.lines().filter(|line| !line.trim().is_empty()) {
             let mut split_iter = line.split_ascii_whitespace();
             let direction = parse_direction(
                 split_iter
@@ -71,26 +147,39 @@ impl AocSolver<'_, usize, usize> for Solver {
                 .next()
                 .ok_or_else(|| InputParseError::new("expected distance".into()))?
                 .parse()?;
-            for _ in 0..distance {
-                rope_part1.move_rope(direction);
-                rope_part2.move_rope(direction);
-                tail_positions_part1.insert(rope_part1.tail());
-                tail_positions_part2.insert(rope_part2.tail());
-            }
+            moves.push((direction, distance));
         }
-
-        Ok(Solver {
-            tail_positions_part1,
-            tail_positions_part2,
-        })
+        Ok(Solver { moves })
     }
 
     fn solve_part1(&self) -> anyhow::Result<usize> {
-        Ok(self.tail_positions_part1.len())
+        Ok(self.simulate(2)?.tail_visited().len())
     }
 
     fn solve_part2(&self) -> anyhow::Result<Option<usize>> {
-        Ok(Some(self.tail_positions_part2.len()))
+        Ok(Some(self.simulate(10)?.tail_visited().len()))
+    }
+
+    fn visualize(&self) -> anyhow::Result<Option<String>> {
+        let simulation = self.simulate(10)?;
+        let tail = simulation.visited.len() - 1;
+        Ok(Some(format!(
+            "{}\n{}",
+            simulation.render_rope(),
+            simulation.render_trail(tail).unwrap_or_default()
+        )))
+    }
+}
+
+impl Solver {
+    pub fn simulate(&self, length: usize) -> anyhow::Result<RopeSimulation> {
+        let mut simulation = RopeSimulation::new(length)?;
+        for &(direction, distance) in &self.moves {
+            for _ in 0..distance {
+                simulation.move_rope(direction);
+            }
+        }
+        Ok(simulation)
     }
 }
 
@@ -98,6 +187,7 @@ impl AocSolver<'_, usize, usize> for Solver {
 mod tests {
     use super::*;
     use crate::solvers::test::test_example_input;
+    use rstest::rstest;
 
     #[test]
     fn test_example() {
@@ -111,4 +201,72 @@ mod tests {
         let solver = Solver::new(input).unwrap();
         assert_eq!(solver.solve_part2().unwrap(), Some(36));
     }
+
+    #[test]
+    fn test_render_example() {
+        let solver = Solver::new(include_str!("examples/day09")).unwrap();
+        let simulation = solver.simulate(2).unwrap();
+        assert_eq!(
+            simulation.render_rope(),
+            "......\n......\n.TH...\n......\ns.....\n"
+        );
+        assert_eq!(
+            simulation.render_trail(1).unwrap(),
+            "..##..\n...##.\n.####.\n....#.\ns###..\n"
+        );
+        assert_eq!(simulation.visited(0).unwrap().len(), 21);
+        assert!(simulation.visited(2).is_none());
+    }
+
+    #[test]
+    fn test_render_larger_example() {
+        let solver = Solver::new(include_str!("examples/day09-large")).unwrap();
+        let simulation = solver.simulate(10).unwrap();
+        let empty = ".".repeat(26);
+        let mut expected: Vec<String> = "H123456789"
+            .chars()
+            .map(|knot| format!("{}{}", knot, &empty[1..]))
+            .collect();
+        expected.extend(std::iter::repeat_n(empty.clone(), 5));
+        expected.push(format!("{}s{}", &empty[..11], &empty[12..]));
+        expected.extend(std::iter::repeat_n(empty, 5));
+        assert_eq!(simulation.render_rope(), expected.join("\n") + "\n");
+        assert_eq!(
+            simulation.render_trail(9).unwrap(),
+            "\
+            ..........................\n\
+            ..........................\n\
+            ..........................\n\
+            ..........................\n\
+            ..........................\n\
+            ..........................\n\
+            ..........................\n\
+            ..........................\n\
+            ..........................\n\
+            #.........................\n\
+            #.............###.........\n\
+            #............#...#........\n\
+            .#..........#.....#.......\n\
+            ..#..........#.....#......\n\
+            ...#........#.......#.....\n\
+            ....#......s.........#....\n\
+            .....#..............#.....\n\
+            ......#............#......\n\
+            .......#..........#.......\n\
+            ........#........#........\n\
+            .........########.........\n"
+        );
+    }
+
+    #[rstest]
+    #[case(1, 21)]
+    #[case(2, 13)]
+    #[case(10, 1)]
+    #[case(12, 1)]
+    fn test_rope_lengths(#[case] length: usize, #[case] expected: usize) {
+        let solver = Solver::new(include_str!("examples/day09")).unwrap();
+        let simulation = solver.simulate(length).unwrap();
+        assert_eq!(simulation.tail_visited().len(), expected);
+        assert!(solver.simulate(0).is_err());
+    }
 }
@@ -66,7 +66,7 @@ impl Cpu {
                 self.screen_buffer[self.cycle / 40][self.cycle % 40] = '█';
             }
             self.cycle += 1;
-            if (self.cycle + 20) % 40 == 0 {
+            if (self.cycle + 20).is_multiple_of(40) {
                 self.accumulated_signal_strength += self.cycle as i64 * self.register_x;
             }
         }
@@ -62,7 +62,7 @@ impl MonkeyBusiness {
             while let Some(item) = monkey.items.pop_front() {
                 monkey.num_items_inspected += 1;
                 let worry_level = (monkey.operation.apply(item) / calm_down_factor) % self.modulus;
-                if worry_level % monkey.divisor == 0 {
+                if worry_level.is_multiple_of(monkey.divisor) {
                     self.monkeys[monkey.target_if_divisible]
                         .borrow_mut()
                         .items
@@ -53,9 +53,13 @@ fn to_height(marker: u8) -> u8 {
     }
 }
 
+type Route = Vec<(usize, usize)>;
+
 pub struct Solver<'a> {
     grid: Grid<'a>,
     target: (usize, usize),
+    start_route: Option<Route>,
+    lowest_route: Option<Route>,
 }
 
 impl<'a> AocSolver<'a, usize, usize> for Solver<'a> {
@@ -64,74 +68,114 @@ impl<'a> AocSolver<'a, usize, usize> for Solver<'a> {
         Self: Sized,
     {
         let grid = Grid::new(input.as_bytes());
-        Ok(Self {
+        let mut solver = Self {
             target: grid
                 .find(b'E')
                 .ok_or_else(|| InputParseError::new("missing target".into()))?,
             grid,
-        })
+            start_route: None,
+            lowest_route: None,
+        };
+        solver.start_route = solver.route(b'S');
+        solver.lowest_route = solver.route(b'a');
+        Ok(solver)
     }
 
     fn solve_part1(&self) -> anyhow::Result<usize> {
-        self.bfs(self.target, b'S')
+        Ok(Self::found(&self.start_route)?.len() - 1)
     }
 
     fn solve_part2(&self) -> anyhow::Result<Option<usize>> {
-        self.bfs(self.target, b'a').map(Some)
+        Ok(Some(Self::found(&self.lowest_route)?.len() - 1))
+    }
+
+    fn visualize(&self) -> anyhow::Result<Option<String>> {
+        Ok(Some(format!(
+            "{}\n{}",
+            self.render_route(Self::found(&self.start_route)?),
+            self.render_route(Self::found(&self.lowest_route)?)
+        )))
     }
 }
 
 impl<'a> Solver<'a> {
-    fn bfs(&self, start: (usize, usize), needle: u8) -> anyhow::Result<usize> {
-        let mut visited: Vec<Vec<bool>> = vec![vec![false; self.grid.width()]; self.grid.height()];
-        struct QueueItem {
-            distance: usize,
-            pos: (usize, usize),
-        }
-        let mut to_visit = std::collections::VecDeque::<QueueItem>::new();
-        visited[start.0][start.1] = true;
-        to_visit.push_back(QueueItem {
-            distance: 0,
-            pos: start,
-        });
-
-        while !to_visit.is_empty() {
-            let QueueItem { distance, pos } = to_visit.pop_front().unwrap();
+    /// Returns the shortest route from the closest position marked with
+    /// `needle` to the target, including both end points.
+    pub fn route(&self, needle: u8) -> Option<Route> {
+        let mut predecessor: Vec<Vec<Option<(usize, usize)>>> =
+            vec![vec![None; self.grid.width()]; self.grid.height()];
+        let mut to_visit = std::collections::VecDeque::<(usize, usize)>::new();
+        predecessor[self.target.0][self.target.1] = Some(self.target);
+        to_visit.push_back(self.target);
+
+        while let Some(pos) = to_visit.pop_front() {
             if self.grid.get(pos) == needle {
-                return Ok(distance);
+                let mut route = vec![pos];
+                let mut current = pos;
+                while current != self.target {
+                    current = predecessor[current.0][current.1].unwrap();
+                    route.push(current);
+                }
+                return Some(route);
             }
 
-            let mut visit = |pos: (usize, usize), new_pos: (usize, usize), distance: usize| {
-                if !visited[new_pos.0][new_pos.1]
+            let mut visit = |new_pos: (usize, usize)| {
+                if predecessor[new_pos.0][new_pos.1].is_none()
                     && (to_height(self.grid.get(pos)) <= 1 + to_height(self.grid.get(new_pos)))
                 {
-                    visited[new_pos.0][new_pos.1] = true;
-                    to_visit.push_back(QueueItem {
-                        distance,
-                        pos: new_pos,
-                    });
+                    predecessor[new_pos.0][new_pos.1] = Some(pos);
+                    to_visit.push_back(new_pos);
                 }
             };
 
             if pos.0 > 0 {
-                let new_pos = (pos.0 - 1, pos.1);
-                visit(pos, new_pos, distance + 1);
+                visit((pos.0 - 1, pos.1));
             }
             if pos.0 < self.grid.height() - 1 {
-                let new_pos = (pos.0 + 1, pos.1);
-                visit(pos, new_pos, distance + 1);
+                visit((pos.0 + 1, pos.1));
             }
             if pos.1 > 0 {
-                let new_pos = (pos.0, pos.1 - 1);
-                visit(pos, new_pos, distance + 1);
+                visit((pos.0, pos.1 - 1));
             }
             if pos.1 < self.grid.width() - 1 {
-                let new_pos = (pos.0, pos.1 + 1);
-                visit(pos, new_pos, distance + 1);
+                visit((pos.0, pos.1 + 1));
             }
         }
 
-        Err(anyhow::anyhow!("No solution found."))
+        None
+    }
+
+    fn found(route: &Option<Route>) -> anyhow::Result<&Route> {
+        route
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No solution found."))
+    }
+
+    /// Renders the height map with the route drawn as arrows in the style of
+    /// the puzzle description.
+    pub fn render_route(&self, route: &[(usize, usize)]) -> String {
+        let mut canvas = vec![vec!['.'; self.grid.width()]; self.grid.height()];
+        for step in route.windows(2) {
+            let (from, to) = (step[0], step[1]);
+            canvas[from.0][from.1] = if to.0 < from.0 {
+                '^'
+            } else if to.0 > from.0 {
+                'v'
+            } else if to.1 < from.1 {
+                '<'
+            } else {
+                '>'
+            };
+        }
+        canvas[self.target.0][self.target.1] = 'E';
+        canvas
+            .into_iter()
+            .map(|row| {
+                row.into_iter()
+                    .chain(std::iter::once('\n'))
+                    .collect::<String>()
+            })
+            .collect()
     }
 }
 
@@ -145,4 +189,29 @@ mod tests {
         let input = include_str!("examples/day12");
         test_example_input::<Solver, _, _>(input, 31, Some(29));
     }
+
+    #[test]
+    fn test_route() {
+        let input = include_str!("examples/day12");
+        let solver = Solver::new(input).unwrap();
+        let route = solver.route(b'S').unwrap();
+        assert_eq!(route.len(), 32);
+        assert_eq!(route.first(), Some(&(0, 0)));
+        assert_eq!(route.last(), Some(&(2, 5)));
+        for step in route.windows(2) {
+            let (from, to) = (step[0], step[1]);
+            assert_eq!(from.0.abs_diff(to.0) + from.1.abs_diff(to.1), 1);
+            assert!(to_height(solver.grid.get(to)) <= to_height(solver.grid.get(from)) + 1);
+        }
+    }
+
+    #[test]
+    fn test_render_route() {
+        let solver = Solver::new("abcdefghijklm\nSyxwvutsrqpon\nEz...........\n").unwrap();
+        let route = solver.route(b'S').unwrap();
+        assert_eq!(
+            solver.render_route(&route),
+            ">>>>>>>>>>>>v\n^v<<<<<<<<<<<\nE<...........\n"
+        );
+    }
 }
@@ -1,29 +1,215 @@
-use std::cmp::Ordering;
+use std::{cmp::Ordering, fmt::Display, str::FromStr};
 
 use super::{base::AocSolver, error::InputParseError};
 
+/// Non-negative integer of arbitrary size stored as decimal digits without
+/// leading zeros.
 #[derive(Clone, Debug, PartialEq, Eq)]
-enum Data {
-    Int(u8),
+pub struct Integer(String);
+
+impl Integer {
+    fn from_digits(digits: &str) -> Result<Self, InputParseError> {
+        if digits.is_empty() || !digits.bytes().all(|c| c.is_ascii_digit()) {
+            return Err(InputParseError::new(format!("invalid integer {}", digits)));
+        }
+        let trimmed = digits.trim_start_matches('0');
+        Ok(Self(if trimmed.is_empty() { "0" } else { trimmed }.into()))
+    }
+}
+
+impl From<u64> for Integer {
+    fn from(value: u64) -> Self {
+        Self(value.to_string())
+    }
+}
+
+impl Ord for Integer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0
+            .len()
+            .cmp(&other.0.len())
+            .then_with(|| self.0.cmp(&other.0))
+    }
+}
+
+impl PartialOrd for Integer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Integer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Packet data, where an integer compares equal to a list containing only
+/// that integer.
+#[derive(Clone, Debug)]
+pub enum Data {
+    Int(Integer),
     List(Vec<Data>),
 }
 
+impl Data {
+    fn fmt_pretty(&self, f: &mut std::fmt::Formatter<'_>, indent: usize) -> std::fmt::Result {
+        match self {
+            Data::Int(value) => write!(f, "{:indent$}{}", "", value, indent = indent),
+            Data::List(list) if list.iter().all(|item| matches!(item, Data::Int(_))) => {
+                write!(f, "{:indent$}{}", "", self, indent = indent)
+            }
+            Data::List(list) => {
+                writeln!(f, "{:indent$}[", "", indent = indent)?;
+                for (i, item) in list.iter().enumerate() {
+                    item.fmt_pretty(f, indent + 2)?;
+                    writeln!(f, "{}", if i + 1 < list.len() { "," } else { "" })?;
+                }
+                write!(f, "{:indent$}]", "", indent = indent)
+            }
+        }
+    }
+
+    /// Compares two packets and explains each step in the style of the
+    /// puzzle description.
+    pub fn compare_trace(&self, other: &Self) -> (Ordering, String) {
+        let mut trace = String::new();
+        let ordering = self.trace_cmp(other, 0, &mut trace);
+        (ordering, trace)
+    }
+
+    fn trace_cmp(&self, other: &Self, depth: usize, trace: &mut String) -> Ordering {
+        let indent = "  ".repeat(depth);
+        trace.push_str(&format!("{}- Compare {} vs {}\n", indent, self, other));
+        let conclude = |trace: &mut String, ordering: Ordering, reason: &str| {
+            let conclusion = if ordering == Ordering::Less {
+                "so inputs are in the right order"
+            } else {
+                "so inputs are not in the right order"
+            };
+            trace.push_str(&format!("{}  - {}, {}\n", indent, reason, conclusion));
+            ordering
+        };
+        match (self, other) {
+            (Data::Int(left), Data::Int(right)) => match left.cmp(right) {
+                Ordering::Equal => Ordering::Equal,
+                Ordering::Less => conclude(trace, Ordering::Less, "Left side is smaller"),
+                Ordering::Greater => conclude(trace, Ordering::Greater, "Right side is smaller"),
+            },
+            (Data::List(left), Data::List(right)) => {
+                for (left, right) in left.iter().zip(right) {
+                    match left.trace_cmp(right, depth + 1, trace) {
+                        Ordering::Equal => {}
+                        ordering => return ordering,
+                    }
+                }
+                match left.len().cmp(&right.len()) {
+                    Ordering::Equal => Ordering::Equal,
+                    Ordering::Less => conclude(trace, Ordering::Less, "Left side ran out of items"),
+                    Ordering::Greater => {
+                        conclude(trace, Ordering::Greater, "Right side ran out of items")
+                    }
+                }
+            }
+            (Data::Int(_), right) => {
+                let converted = Data::List(vec![self.clone()]);
+                trace.push_str(&format!(
+                    "{}  - Mixed types; convert left to {} and retry comparison\n",
+                    indent, converted
+                ));
+                converted.trace_cmp(right, depth + 1, trace)
+            }
+            (left, Data::Int(_)) => {
+                let converted = Data::List(vec![other.clone()]);
+                trace.push_str(&format!(
+                    "{}  - Mixed types; convert right to {} and retry comparison\n",
+                    indent, converted
+                ));
+                left.trace_cmp(&converted, depth + 1, trace)
+            }
+        }
+    }
+}
+
+impl Ord for Data {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Data::Int(left), Data::Int(right)) => left.cmp(right),
+            (Data::List(left), Data::List(right)) => left.cmp(right),
+            (Data::Int(_), Data::List(right)) => std::slice::from_ref(self).cmp(right.as_slice()),
+            (Data::List(left), Data::Int(_)) => left.as_slice().cmp(std::slice::from_ref(other)),
+        }
+    }
+}
+
+impl PartialEq for Data {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Data {}
+
+impl PartialOrd for Data {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl FromStr for Data {
+    type Err = InputParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (data, rest) = parse_data(s)?;
+        if !rest.is_empty() {
+            return Err(InputParseError::new(format!(
+                "unexpected trailing {}",
+                rest
+            )));
+        }
+        Ok(data)
+    }
+}
+
+/// Formats packets in the input format, or indented over multiple lines
+/// with the alternate flag (`{:#}`).
+impl Display for Data {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if f.alternate() {
+            return self.fmt_pretty(f, 0);
+        }
+        match self {
+            Data::Int(value) => write!(f, "{}", value),
+            Data::List(list) => {
+                f.write_str("[")?;
+                for (i, item) in list.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                f.write_str("]")
+            }
+        }
+    }
+}
+
 type ParseResult<'a, T> = Result<(T, &'a str), InputParseError>;
 
-fn parse_pair(input: &str) -> ParseResult<(Data, Data)> {
+fn parse_pair(input: &str) -> ParseResult<'_, (Data, Data)> {
     let (packet0, input) = parse_packet(input)?;
     let (packet1, input) = parse_packet(input)?;
     let input = skip_optional_char(input, '\n');
     Ok(((packet0, packet1), input))
 }
 
-fn parse_packet(input: &str) -> ParseResult<Data> {
+fn parse_packet(input: &str) -> ParseResult<'_, Data> {
     let (list, input) = parse_list(input)?;
     let input = skip_optional_char(input, '\n');
     Ok((Data::List(list), input))
 }
 
-fn parse_data(input: &str) -> ParseResult<Data> {
+fn parse_data(input: &str) -> ParseResult<'_, Data> {
     if let Ok((list, input)) = parse_list(input) {
         Ok((Data::List(list), input))
     } else if let Ok((int, input)) = parse_int(input) {
@@ -33,7 +219,7 @@ fn parse_data(input: &str) -> ParseResult<Data> {
     }
 }
 
-fn parse_list(input: &str) -> ParseResult<Vec<Data>> {
+fn parse_list(input: &str) -> ParseResult<'_, Vec<Data>> {
     let mut list = vec![];
     let (_, mut input) = skip_char(input, '[')?;
     if let Ok((data, new_input)) = parse_data(input) {
@@ -50,15 +236,14 @@ fn parse_list(input: &str) -> ParseResult<Vec<Data>> {
     Ok((list, input))
 }
 
-fn parse_int(input: &str) -> ParseResult<u8> {
-    if let Some((prefix, _)) = input.split_once(|c: char| !c.is_ascii_digit()) {
-        Ok((prefix.parse()?, &input[prefix.len()..]))
-    } else {
-        Ok((input.parse()?, &input[input.len()..]))
-    }
+fn parse_int(input: &str) -> ParseResult<'_, Integer> {
+    let len = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    Ok((Integer::from_digits(&input[..len])?, &input[len..]))
 }
 
-fn skip_char(input: &str, c: char) -> ParseResult<()> {
+fn skip_char(input: &str, c: char) -> ParseResult<'_, ()> {
     if input.starts_with(c) {
         Ok(((), &input[1..]))
     } else {
@@ -74,30 +259,6 @@ fn skip_optional_char(input: &str, c: char) -> &str {
     }
 }
 
-fn has_right_order(left: &Data, right: &Data) -> Ordering {
-    match (left, right) {
-        (Data::Int(left_value), Data::Int(right_value)) => left_value.cmp(right_value),
-        (Data::List(left_value), Data::List(right_value)) => {
-            for i in 0..left_value.len().min(right_value.len()) {
-                match has_right_order(&left_value[i], &right_value[i]) {
-                    Ordering::Equal => {}
-                    result => return result,
-                }
-            }
-            has_right_order(
-                &Data::Int(left_value.len() as u8),
-                &Data::Int(right_value.len() as u8),
-            )
-        }
-        (Data::Int(left_value), right) => {
-            has_right_order(&Data::List(vec![Data::Int(*left_value)]), right)
-        }
-        (left, Data::Int(right_value)) => {
-            has_right_order(left, &Data::List(vec![Data::Int(*right_value)]))
-        }
-    }
-}
-
 pub struct Solver {
     packet_pairs: Vec<(Data, Data)>,
 }
@@ -120,7 +281,7 @@ impl<'a> AocSolver<'a, usize, usize> for Solver {
         Ok(self
             .packet_pairs
             .iter()
-            .map(|pair| has_right_order(&pair.0, &pair.1))
+            .map(|pair| pair.0.cmp(&pair.1))
             .enumerate()
             .filter_map(|(i, ordering)| match ordering {
                 Ordering::Less => Some(i + 1),
@@ -135,19 +296,19 @@ impl<'a> AocSolver<'a, usize, usize> for Solver {
             packets.push(p0);
             packets.push(p1);
         }
-        let divider0 = Data::List(vec![Data::List(vec![Data::Int(2)])]);
-        let divider1 = Data::List(vec![Data::List(vec![Data::Int(6)])]);
+        let divider0 = Data::List(vec![Data::List(vec![Data::Int(2.into())])]);
+        let divider1 = Data::List(vec![Data::List(vec![Data::Int(6.into())])]);
         packets.push(&divider0);
         packets.push(&divider1);
 
-        packets.sort_by(|&a, &b| has_right_order(a, b));
+        packets.sort();
 
         Ok(Some(
             packets
                 .iter()
                 .enumerate()
                 .filter_map(|(i, &p)| {
-                    if *p == divider0 || *p == divider1 {
+                    if std::ptr::eq(p, &divider0) || std::ptr::eq(p, &divider1) {
                         Some(i + 1)
                     } else {
                         None
@@ -156,6 +317,18 @@ impl<'a> AocSolver<'a, usize, usize> for Solver {
                 .product(),
         ))
     }
+
+    fn visualize(&self) -> anyhow::Result<Option<String>> {
+        Ok(Some(
+            self.packet_pairs
+                .iter()
+                .enumerate()
+                .map(|(i, (left, right))| {
+                    format!("== Pair {} ==\n{}\n", i + 1, left.compare_trace(right).1)
+                })
+                .collect(),
+        ))
+    }
 }
 
 #[cfg(test)]
@@ -168,4 +341,71 @@ mod tests {
         let input = include_str!("examples/day13");
         test_example_input::<Solver, _, _>(input, 13, Some(140));
     }
+
+    #[test]
+    fn test_round_trip() {
+        for line in include_str!("examples/day13").lines() {
+            if !line.is_empty() {
+                assert_eq!(line.parse::<Data>().unwrap().to_string(), line);
+            }
+        }
+    }
+
+    #[test]
+    fn test_large_integers() {
+        let small: Data = "[255,99999999999999999999]".parse().unwrap();
+        let large: Data = "[256,100000000000000000000]".parse().unwrap();
+        assert!(small < large);
+        assert_eq!(
+            "[99999999999999999999,1]"
+                .parse::<Data>()
+                .unwrap()
+                .cmp(&"[099999999999999999999,0]".parse::<Data>().unwrap()),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!("[1,2".parse::<Data>().is_err());
+        assert!("[1,2]]".parse::<Data>().is_err());
+        assert!("[1,,2]".parse::<Data>().is_err());
+    }
+
+    #[test]
+    fn test_pretty() {
+        let data: Data = "[1,[2,[3,4]],[]]".parse().unwrap();
+        assert_eq!(
+            format!("{:#}", data),
+            "[\n  1,\n  [\n    2,\n    [3,4]\n  ],\n  []\n]"
+        );
+    }
+
+    #[test]
+    fn test_compare_trace() {
+        let left: Data = "[[1],[2,3,4]]".parse().unwrap();
+        let right: Data = "[[1],4]".parse().unwrap();
+        assert_eq!(
+            left.compare_trace(&right),
+            (
+                Ordering::Less,
+                "- Compare [[1],[2,3,4]] vs [[1],4]\n\
+                 \x20 - Compare [1] vs [1]\n\
+                 \x20   - Compare 1 vs 1\n\
+                 \x20 - Compare [2,3,4] vs 4\n\
+                 \x20   - Mixed types; convert right to [4] and retry comparison\n\
+                 \x20   - Compare [2,3,4] vs [4]\n\
+                 \x20     - Compare 2 vs 4\n\
+                 \x20       - Left side is smaller, so inputs are in the right order\n"
+                    .into()
+            )
+        );
+
+        let left: Data = "[7,7,7,7]".parse().unwrap();
+        let right: Data = "[7,7,7]".parse().unwrap();
+        let (ordering, trace) = left.compare_trace(&right);
+        assert_eq!(ordering, Ordering::Greater);
+        assert!(trace
+            .ends_with("  - Right side ran out of items, so inputs are not in the right order\n"));
+    }
 }
@@ -1,35 +1,53 @@
+use std::{collections::HashMap, fmt::Display};
+
 use super::{base::AocSolver, error::InputParseError};
 
-type Point = (usize, usize);
+pub type Point = (i64, i64);
 type Line = Vec<Point>;
 
+pub const SAND_SOURCE: Point = (500, 0);
+pub const GRAINS_PER_FRAME: usize = 100;
+
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-enum Element {
+pub enum Element {
     Air,
     Rock,
     Sand,
 }
 
+/// Cave with sparse storage, so that rock and sand may be anywhere.
 #[derive(Debug)]
-struct Cave {
-    min_x: usize,
-    max_x: usize,
-    max_y: usize,
-    map: Vec<Element>,
+pub struct Cave {
+    source: Point,
+    max_y: i64,
+    floor: Option<i64>,
+    map: HashMap<Point, Element>,
+    /// Path of the last grain of sand. The next grain follows the same path
+    /// up to the position where the last one came to rest.
+    path: Vec<Point>,
 }
 
 impl Cave {
-    pub fn new(min_x: usize, max_x: usize, max_y: usize) -> Self {
+    pub fn new(source: Point) -> Self {
         Self {
-            min_x,
-            max_x,
-            max_y,
-            map: vec![Element::Air; (max_x + 1 - min_x + 2) * (max_y + 1)],
+            source,
+            max_y: source.1,
+            floor: None,
+            map: HashMap::new(),
+            path: vec![],
         }
     }
 
-    fn point2idx(&self, point: &Point) -> usize {
-        (point.1 * (self.max_x - self.min_x + 1 + 2)) + (point.0 + 1 - self.min_x)
+    pub fn get(&self, point: &Point) -> Element {
+        if self.floor == Some(point.1) {
+            return Element::Rock;
+        }
+        self.map.get(point).copied().unwrap_or(Element::Air)
+    }
+
+    /// Adds an infinite floor two below the lowest rock.
+    pub fn add_floor(&mut self) {
+        self.floor = Some(self.max_y + 2);
     }
 
     pub fn add_rock(&mut self, line: &[Point]) {
@@ -39,79 +57,253 @@ impl Cave {
     }
 
     pub fn add_rock_between_points(&mut self, start: &Point, end: &Point) {
-        if start.0 == end.0 {
-            let (start, end) = if start.1 > end.1 {
-                (end, start)
-            } else {
-                (start, end)
-            };
-            for y in start.1..=end.1 {
-                let idx = self.point2idx(&(start.0, y));
-                self.map[idx] = Element::Rock;
-            }
-        } else if start.1 == end.1 {
-            let (start, end) = if start.0 > end.0 {
-                (end, start)
-            } else {
-                (start, end)
-            };
-            for x in start.0..=end.0 {
-                let idx = self.point2idx(&(x, start.1));
-                self.map[idx] = Element::Rock;
-            }
-        } else {
+        if start.0 != end.0 && start.1 != end.1 {
             panic!("must be called with horizontal or vertical line");
         }
+        for x in start.0.min(end.0)..=start.0.max(end.0) {
+            for y in start.1.min(end.1)..=start.1.max(end.1) {
+                self.map.insert((x, y), Element::Rock);
+                self.max_y = self.max_y.max(y);
+            }
+        }
     }
 
     pub fn fill_with_sand(&mut self) -> usize {
         let mut count = 0;
         while self.drop_sand().is_some() {
             count += 1;
-            if self.map[self.point2idx(&(500, 0))] == Element::Sand {
-                break;
-            }
         }
         count
     }
 
     fn drop_sand(&mut self) -> Option<Point> {
-        let mut current_pos = (500, 0);
+        if self.path.is_empty() {
+            if self.get(&self.source) != Element::Air {
+                return None;
+            }
+            self.path.push(self.source);
+        }
         loop {
-            if current_pos.1 >= self.max_y {
+            let current_pos = *self.path.last()?;
+            if self.floor.is_none() && current_pos.1 >= self.max_y {
                 return None;
             }
-            if self.map[self.point2idx(&(current_pos.0, current_pos.1 + 1))] == Element::Air {
-                current_pos = (current_pos.0, current_pos.1 + 1);
-            } else if self.map[self.point2idx(&(current_pos.0 - 1, current_pos.1 + 1))]
-                == Element::Air
-            {
-                current_pos = (current_pos.0 - 1, current_pos.1 + 1);
-            } else if self.map[self.point2idx(&(current_pos.0 + 1, current_pos.1 + 1))]
-                == Element::Air
-            {
-                current_pos = (current_pos.0 + 1, current_pos.1 + 1);
-            } else {
-                let idx = self.point2idx(&current_pos);
-                self.map[idx] = Element::Sand;
-                return Some(current_pos);
+            let next_pos = [0, -1, 1]
+                .into_iter()
+                .map(|dx| (current_pos.0 + dx, current_pos.1 + 1))
+                .find(|pos| self.get(pos) == Element::Air);
+            match next_pos {
+                Some(next_pos) => self.path.push(next_pos),
+                None => {
+                    self.map.insert(current_pos, Element::Sand);
+                    self.path.pop();
+                    return Some(current_pos);
+                }
+            }
+        }
+    }
+
+    /// Region containing all rock and any sand that may come to rest.
+    fn bounds(&self) -> (Point, Point) {
+        let mut min = self.source;
+        let mut max = self.source;
+        for point in self.map.keys() {
+            min = (min.0.min(point.0), min.1.min(point.1));
+            max = (max.0.max(point.0), max.1.max(point.1));
+        }
+        if let Some(floor) = self.floor {
+            let depth = floor - self.source.1;
+            min.0 = min.0.min(self.source.0 - depth);
+            max.0 = max.0.max(self.source.0 + depth);
+            max.1 = floor;
+        }
+        ((min.0 - 1, min.1), (max.0 + 1, max.1))
+    }
+
+    pub fn snapshot(&self, grains: usize, bounds: (Point, Point)) -> Snapshot {
+        let ((min_x, min_y), (max_x, max_y)) = bounds;
+        Snapshot {
+            grains,
+            min: (min_x, min_y),
+            width: (max_x - min_x + 1) as usize,
+            height: (max_y - min_y + 1) as usize,
+            cells: (min_y..=max_y)
+                .flat_map(|y| (min_x..=max_x).map(move |x| (x, y)))
+                .map(|point| self.get(&point))
+                .collect(),
+            source: self.source,
+        }
+    }
+
+    /// Drops sand and yields the state of the cave after every
+    /// `grains_per_frame` grains and once more after the last grain. All
+    /// frames share the same bounds.
+    pub fn frames(&mut self, grains_per_frame: usize) -> Frames<'_> {
+        Frames {
+            bounds: self.bounds(),
+            cave: self,
+            grains_per_frame,
+            grains: 0,
+            done: false,
+        }
+    }
+
+    /// Number of grains that come to rest before the source is blocked,
+    /// computed row by row without simulating single grains. With a floor
+    /// every cell reachable from the source by moving down, down-left or
+    /// down-right gets filled. Returns `None` if the cave has no floor.
+    pub fn count_floor_fill(&self) -> Option<usize> {
+        let floor = self.floor?;
+        let depth = floor - self.source.1;
+        if depth <= 0 || self.get(&self.source) != Element::Air {
+            return Some(0);
+        }
+
+        let min_x = self.source.0 - depth;
+        let mut row = vec![false; 2 * depth as usize + 1];
+        row[depth as usize] = true;
+        let mut count = 1;
+        for y in self.source.1 + 1..floor {
+            let next_row: Vec<bool> = (0..row.len())
+                .map(|i| {
+                    let reachable =
+                        row[i] || (i > 0 && row[i - 1]) || row.get(i + 1).copied().unwrap_or(false);
+                    reachable && self.get(&(min_x + i as i64, y)) == Element::Air
+                })
+                .collect();
+            count += next_row.iter().filter(|&&reachable| reachable).count();
+            row = next_row;
+        }
+        Some(count)
+    }
+}
+
+pub struct Frames<'a> {
+    cave: &'a mut Cave,
+    bounds: (Point, Point),
+    grains_per_frame: usize,
+    grains: usize,
+    done: bool,
+}
+
+impl Iterator for Frames<'_> {
+    type Item = Snapshot;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let mut dropped = 0;
+        while dropped < self.grains_per_frame {
+            if self.cave.drop_sand().is_none() {
+                self.done = true;
+                break;
+            }
+            dropped += 1;
+        }
+        if dropped == 0 {
+            return None;
+        }
+        self.grains += dropped;
+        Some(self.cave.snapshot(self.grains, self.bounds))
+    }
+}
+
+/// State of a rectangular region of the cave after some grains of sand.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    /// Number of grains dropped so far.
+    pub grains: usize,
+    /// Top left corner of the region.
+    pub min: Point,
+    pub width: usize,
+    pub height: usize,
+    cells: Vec<Element>,
+    source: Point,
+}
+
+impl Snapshot {
+    fn symbols(&self) -> impl Iterator<Item = char> + '_ {
+        self.cells.iter().enumerate().map(|(i, element)| {
+            let point = (
+                self.min.0 + (i % self.width) as i64,
+                self.min.1 + (i / self.width) as i64,
+            );
+            match element {
+                Element::Air if point == self.source => '+',
+                Element::Air => '.',
+                Element::Rock => '#',
+                Element::Sand => 'o',
             }
+        })
+    }
+
+    /// Renders the snapshot as plain PPM image, or as plain PGM image if
+    /// `color` is false, with one pixel per cell.
+    pub fn to_netpbm(&self, color: bool) -> String {
+        let mut image = format!(
+            "{}\n{} {}\n255\n",
+            if color { "P3" } else { "P2" },
+            self.width,
+            self.height
+        );
+        let symbols: Vec<char> = self.symbols().collect();
+        for row in symbols.chunks(self.width) {
+            let pixels: Vec<String> = row
+                .iter()
+                .map(|symbol| {
+                    let (rgb, gray) = match symbol {
+                        '#' => ((96, 96, 96), 96),
+                        'o' => ((230, 190, 90), 192),
+                        '+' => ((220, 40, 40), 128),
+                        _ => ((16, 16, 32), 16),
+                    };
+                    if color {
+                        format!("{} {} {}", rgb.0, rgb.1, rgb.2)
+                    } else {
+                        gray.to_string()
+                    }
+                })
+                .collect();
+            image.push_str(&pixels.join(" "));
+            image.push('\n');
+        }
+        image
+    }
+}
+
+/// Renders the snapshot in the style of the puzzle description.
+impl Display for Snapshot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let symbols: Vec<char> = self.symbols().collect();
+        for row in symbols.chunks(self.width) {
+            writeln!(f, "{}", row.iter().collect::<String>())?;
         }
+        Ok(())
     }
 }
 
+/// Parses a point in the format `x,y`.
+pub fn parse_point(point_def: &str) -> Result<Point, InputParseError> {
+    let mut coords = point_def.split(',').map(str::parse);
+    Ok((
+        coords
+            .next()
+            .ok_or_else(|| InputParseError::new("missing coordinate".into()))??,
+        coords
+            .next()
+            .ok_or_else(|| InputParseError::new("missing coordinate".into()))??,
+    ))
+}
+
 pub struct Solver {
     lines: Vec<Line>,
-    min_x: usize,
-    max_x: usize,
-    max_y: usize,
+    source: Point,
 }
 
-impl<'a> AocSolver<'a, usize, usize> for Solver {
-    fn new(input: &'a str) -> anyhow::Result<Self>
-    where
-        Self: Sized,
-    {
+impl Solver {
+    /// Solver with sand pouring in from `source` instead of `SAND_SOURCE`.
+    pub fn with_source(input: &str, source: Point) -> anyhow::Result<Self> {
         let lines: Vec<Line> = input
             .split('\n')
             .filter_map(|line| {
@@ -120,69 +312,61 @@ impl<'a> AocSolver<'a, usize, usize> for Solver {
                 }
                 Some(
                     line.split(" -> ")
-                        .map(|point_def| {
-                            let mut coords = point_def.split(',').map(str::parse);
-                            Ok((
-                                coords.next().ok_or_else(|| {
-                                    InputParseError::new("missing coordinate".into())
-                                })??,
-                                coords.next().ok_or_else(|| {
-                                    InputParseError::new("missing coordinate".into())
-                                })??,
-                            ))
-                        })
+                        .map(parse_point)
                         .collect::<Result<Vec<Point>, InputParseError>>(),
                 )
             })
             .collect::<Result<Vec<Line>, InputParseError>>()?;
-        let min_x = *lines
-            .iter()
-            .flatten()
-            .map(|(x, _)| x)
-            .min()
-            .ok_or_else(|| InputParseError::new("no rock".into()))?;
-        let max_x = *lines
-            .iter()
-            .flatten()
-            .map(|(x, _)| x)
-            .max()
-            .ok_or_else(|| InputParseError::new("no rock".into()))?;
-        let max_y = *lines
-            .iter()
-            .flatten()
-            .map(|(_, y)| y)
-            .max()
-            .ok_or_else(|| InputParseError::new("no rock".into()))?;
-
-        Ok(Self {
-            lines,
-            min_x,
-            max_x,
-            max_y,
-        })
+        if lines.iter().all(|line| line.is_empty()) {
+            return Err(InputParseError::new("no rock".into()).into());
+        }
+
+        Ok(Self { lines, source })
     }
 
-    fn solve_part1(&self) -> anyhow::Result<usize> {
-        let mut cave = Cave::new(self.min_x, self.max_x, self.max_y);
+    /// Cave with the rock from the input and the configured sand source.
+    pub fn cave(&self) -> Cave {
+        let mut cave = Cave::new(self.source);
         for line in &self.lines {
             cave.add_rock(line);
         }
+        cave
+    }
+}
+
+impl<'a> AocSolver<'a, usize, usize> for Solver {
+    fn new(input: &'a str) -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        Self::with_source(input, SAND_SOURCE)
+    }
 
-        Ok(cave.fill_with_sand())
+    fn solve_part1(&self) -> anyhow::Result<usize> {
+        Ok(self.cave().fill_with_sand())
     }
 
     fn solve_part2(&self) -> anyhow::Result<Option<usize>> {
-        let max_y = self.max_y + 2;
-        let mut cave = Cave::new(
-            self.min_x.min(500 - max_y),
-            self.max_x.max(500 + max_y),
-            max_y,
-        );
-        for line in &self.lines {
-            cave.add_rock(line)
+        let mut cave = self.cave();
+        cave.add_floor();
+        Ok(cave.count_floor_fill())
+    }
+
+    fn visualize(&self) -> anyhow::Result<Option<String>> {
+        let mut output = String::new();
+        for frame in self.cave().frames(GRAINS_PER_FRAME) {
+            output.push_str(&format!("After {} grains:\n{}\n", frame.grains, frame));
         }
-        cave.add_rock(&[(500 - max_y, max_y), (500 + max_y, max_y)]);
-        Ok(Some(cave.fill_with_sand()))
+
+        let mut cave = self.cave();
+        cave.add_floor();
+        if let Some(frame) = cave.frames(usize::MAX).last() {
+            output.push_str(&format!(
+                "With floor after {} grains:\n{}",
+                frame.grains, frame
+            ));
+        }
+        Ok(Some(output))
     }
 }
 
@@ -190,10 +374,176 @@ impl<'a> AocSolver<'a, usize, usize> for Solver {
 mod tests {
     use super::*;
     use crate::solvers::test::test_example_input;
+    use rstest::rstest;
 
     #[test]
     fn test_example() {
         let input = include_str!("examples/day14");
         test_example_input::<Solver, _, _>(input, 24, Some(93));
     }
+
+    fn shifted_solver(dx: i64) -> Solver {
+        let mut solver = Solver::new(include_str!("examples/day14")).unwrap();
+        for point in solver.lines.iter_mut().flatten() {
+            point.0 += dx;
+        }
+        solver
+    }
+
+    #[rstest]
+    #[case(0, (500, 0))]
+    #[case(0, (497, 3))]
+    #[case(0, (503, 1))]
+    #[case(-1000, (-500, 0))]
+    fn test_floor_fill_matches_simulation(#[case] dx: i64, #[case] source: Point) {
+        let solver = Solver {
+            source,
+            ..shifted_solver(dx)
+        };
+        let mut cave = solver.cave();
+        cave.add_floor();
+        let expected = cave.fill_with_sand();
+        cave.map.retain(|_, element| *element == Element::Rock);
+        assert_eq!(cave.count_floor_fill(), Some(expected));
+    }
+
+    #[test]
+    fn test_shifted_cave() {
+        let solver = Solver {
+            source: (-500, 0),
+            ..shifted_solver(-1000)
+        };
+        assert_eq!(solver.cave().fill_with_sand(), 24);
+        let mut cave = solver.cave();
+        cave.add_floor();
+        assert_eq!(cave.count_floor_fill(), Some(93));
+    }
+
+    #[rstest]
+    #[case(0, SAND_SOURCE, 24, 93)]
+    #[case(0, (500, -3), 24, 168)]
+    #[case(0, (490, 0), 0, 106)]
+    #[case(-1000, (-500, 0), 24, 93)]
+    fn test_with_source(
+        #[case] dx: i64,
+        #[case] source: Point,
+        #[case] expected_part1: usize,
+        #[case] expected_part2: usize,
+    ) {
+        let input: String = include_str!("examples/day14")
+            .lines()
+            .map(|line| {
+                let points: Vec<String> = line
+                    .split(" -> ")
+                    .map(|point_def| {
+                        let (x, y) = parse_point(point_def).unwrap();
+                        format!("{},{}", x + dx, y)
+                    })
+                    .collect();
+                points.join(" -> ") + "\n"
+            })
+            .collect();
+        let solver = Solver::with_source(&input, source).unwrap();
+        assert_eq!(solver.solve_part1().unwrap(), expected_part1);
+        assert_eq!(solver.solve_part2().unwrap(), Some(expected_part2));
+    }
+
+    #[test]
+    fn test_blocked_source() {
+        let solver = Solver {
+            source: (498, 4),
+            ..shifted_solver(0)
+        };
+        let mut cave = solver.cave();
+        assert_eq!(cave.fill_with_sand(), 0);
+        cave.add_floor();
+        assert_eq!(cave.count_floor_fill(), Some(0));
+        assert_eq!(solver.cave().count_floor_fill(), None);
+    }
+
+    #[test]
+    fn test_frames() {
+        let solver = shifted_solver(0);
+        let mut cave = solver.cave();
+        let frames: Vec<Snapshot> = cave.frames(22).collect();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].grains, 22);
+        assert_eq!(
+            frames[0].to_string(),
+            "\
+            .......+....\n\
+            ............\n\
+            .......o....\n\
+            ......ooo...\n\
+            .....#ooo##.\n\
+            .....#ooo#..\n\
+            ...###ooo#..\n\
+            .....oooo#..\n\
+            ....ooooo#..\n\
+            .#########..\n"
+        );
+        assert_eq!(frames[1].grains, 24);
+        assert_eq!(
+            frames[1].to_string(),
+            "\
+            .......+....\n\
+            ............\n\
+            .......o....\n\
+            ......ooo...\n\
+            .....#ooo##.\n\
+            ....o#ooo#..\n\
+            ...###ooo#..\n\
+            .....oooo#..\n\
+            ..o.ooooo#..\n\
+            .#########..\n"
+        );
+        assert_eq!(cave.frames(1).count(), 0);
+    }
+
+    #[test]
+    fn test_floor_frames() {
+        let solver = shifted_solver(0);
+        let mut cave = solver.cave();
+        cave.add_floor();
+        let frames: Vec<Snapshot> = cave.frames(10).collect();
+        assert_eq!(frames.len(), 10);
+        let last = frames.last().unwrap();
+        assert_eq!(last.grains, 93);
+        assert_eq!(last.width, 25);
+        assert_eq!(last.height, 12);
+        assert_eq!(
+            last.to_string(),
+            "\
+            ............o............\n\
+            ...........ooo...........\n\
+            ..........ooooo..........\n\
+            .........ooooooo.........\n\
+            ........oo#ooo##o........\n\
+            .......ooo#ooo#ooo.......\n\
+            ......oo###ooo#oooo......\n\
+            .....oooo.oooo#ooooo.....\n\
+            ....oooooooooo#oooooo....\n\
+            ...ooo#########ooooooo...\n\
+            ..ooooo.......ooooooooo..\n\
+            #########################\n"
+        );
+    }
+
+    #[test]
+    fn test_netpbm() {
+        let mut cave = Cave::new((1, 0));
+        cave.add_rock(&[(0, 1), (2, 1)]);
+        let frame = cave.frames(1).next().unwrap();
+        assert_eq!(frame.to_string(), "..o..\n.###.\n");
+        assert_eq!(
+            frame.to_netpbm(false),
+            "P2\n5 2\n255\n16 16 192 16 16\n16 96 96 96 16\n"
+        );
+        let ppm = frame.to_netpbm(true);
+        assert!(ppm.starts_with("P3\n5 2\n255\n16 16 32 "));
+        assert_eq!(
+            ppm.lines().nth(3).unwrap(),
+            "16 16 32 16 16 32 230 190 90 16 16 32 16 16 32"
+        );
+    }
 }
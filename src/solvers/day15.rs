@@ -1,31 +1,21 @@
 use std::collections::HashSet;
 
-use super::{base::AocSolver, error::InputParseError};
+use super::{
+    base::AocSolver,
+    error::InputParseError,
+    interval::{Interval, IntervalSet},
+};
 use lazy_static::lazy_static;
 use regex::Regex;
 
 type Pos = (i64, i64);
 
+const MAX_CELLS_SHOWN: usize = 20;
+
 fn manhatten(a: Pos, b: Pos) -> i64 {
     (a.0 - b.0).abs() + (a.1 - b.1).abs()
 }
 
-#[derive(Clone, Copy, Debug)]
-struct Interval {
-    start: i64,
-    end: i64,
-}
-
-impl Interval {
-    fn new(start: i64, end: i64) -> Self {
-        Self { start, end }
-    }
-
-    fn len(&self) -> usize {
-        ((self.end - self.start) + 1) as usize
-    }
-}
-
 #[derive(Clone, Copy, Debug)]
 struct Reading {
     pub sensor: Pos,
@@ -85,116 +75,200 @@ impl<'a> AocSolver<'a, usize, i64> for Solver {
     fn solve_part2(&self) -> anyhow::Result<Option<i64>> {
         Ok(Some(self.tuning_frequency(4_000_000)?))
     }
-}
 
-impl Solver {
-    fn count_beacons_in_interval(&self, interval: Interval, row: i64) -> usize {
-        self.beacons
-            .iter()
-            .filter(|beacon| {
-                beacon.1 == row && interval.start <= beacon.0 && beacon.0 <= interval.end
-            })
-            .count()
+    fn visualize(&self) -> anyhow::Result<Option<String>> {
+        let area = Rect::new((0, 0), (4_000_000, 4_000_000));
+        let mut output = format!("Uncovered positions in {:?} to {:?}:\n", area.min, area.max);
+        for region in self.uncovered_regions(area) {
+            let mut cells = region.cells();
+            let shown: Vec<String> = cells
+                .by_ref()
+                .take(MAX_CELLS_SHOWN)
+                .map(|cell| format!("{:?}", cell))
+                .collect();
+            output.push_str(&format!(
+                "Region u={}..={}, v={}..={}: {}{}\n",
+                region.rotated.min.0,
+                region.rotated.max.0,
+                region.rotated.min.1,
+                region.rotated.max.1,
+                shown.join(" "),
+                if cells.next().is_some() { " ..." } else { "" }
+            ));
+        }
+        Ok(Some(output))
     }
+}
 
+impl Solver {
     fn count_non_beacon_fields_in_row(&self, row: i64) -> anyhow::Result<usize> {
-        let mut intervals: Vec<Interval> = self
+        let covered: IntervalSet<i64> = self
             .readings
             .iter()
-            .filter_map(|reading| {
+            .map(|reading| {
                 let delta_x = reading.dist() - (reading.sensor.1 - row).abs();
-                if delta_x > 0 {
-                    Some(Interval::new(
-                        reading.sensor.0 - delta_x,
-                        reading.sensor.0 + delta_x,
-                    ))
-                } else {
-                    None
-                }
+                Interval::new(reading.sensor.0 - delta_x, reading.sensor.0 + delta_x)
             })
             .collect();
-        intervals.sort_by_key(|interval| interval.start);
-
-        let mut no_beacon_count: usize =
-            intervals[0].len() - self.count_beacons_in_interval(intervals[0], row);
-        let mut current_interval: Interval = intervals[0];
-        for interval in intervals {
-            if interval.end > current_interval.end {
-                if interval.start > current_interval.end {
-                    no_beacon_count +=
-                        interval.len() - self.count_beacons_in_interval(interval, row);
-                    current_interval = interval;
-                } else {
-                    let interval = Interval::new(current_interval.end + 1, interval.end);
-                    no_beacon_count +=
-                        interval.len() - self.count_beacons_in_interval(interval, row);
-                    current_interval.end = interval.end;
-                }
-            }
+        let beacons: IntervalSet<i64> = self
+            .beacons
+            .iter()
+            .filter(|beacon| beacon.1 == row)
+            .map(|beacon| Interval::new(beacon.0, beacon.0))
+            .collect();
+        Ok(covered.difference(&beacons).len())
+    }
+
+    /// Regions of `area` not covered by any sensor. Each sensor covers a
+    /// diamond, which becomes a square in coordinates rotated by 45°
+    /// (`u = x + y`, `v = x - y`), where it can be subtracted like any
+    /// rectangle.
+    fn uncovered_regions(&self, area: Rect) -> Vec<Region> {
+        let mut remaining = vec![Rect::new(
+            (area.min.0 + area.min.1, area.min.0 - area.max.1),
+            (area.max.0 + area.max.1, area.max.0 - area.min.1),
+        )];
+        for reading in &self.readings {
+            let (u, v) = rotate(reading.sensor);
+            let dist = reading.dist();
+            let covered = Rect::new((u - dist, v - dist), (u + dist, v + dist));
+            remaining = remaining
+                .into_iter()
+                .flat_map(|rect| rect.subtract(&covered))
+                .collect();
         }
+        remaining
+            .into_iter()
+            .map(|rotated| Region { rotated, area })
+            .filter(|region| region.cells().next().is_some())
+            .collect()
+    }
 
-        Ok(no_beacon_count)
+    fn uncovered_cells(&self, area: Rect) -> impl Iterator<Item = Pos> {
+        self.uncovered_regions(area)
+            .into_iter()
+            .flat_map(|region| region.cells())
     }
 
     fn tuning_frequency(&self, max_coordinate: i64) -> anyhow::Result<i64> {
-        let mut positive_slope_intercepts = HashSet::new();
-        let mut negative_slope_intercepts = HashSet::new();
-
-        for (b_start_idx, reading_a) in self.readings.iter().enumerate() {
-            for reading_b in &self.readings[b_start_idx..] {
-                let sensor_dist = manhatten(reading_a.sensor, reading_b.sensor);
-                let covered_dist = reading_a.dist() + reading_b.dist();
-                let gap = (sensor_dist - covered_dist) / 2;
-                if gap == 1 || gap == 2 {
-                    let delta_x = reading_b.sensor.0 - reading_a.sensor.0;
-                    let delta_y = reading_b.sensor.1 - reading_a.sensor.1;
-                    if delta_x == 0 || delta_y == 0 {
-                        // skip, special case not accounted for
-                    } else {
-                        let sign = (delta_x * delta_y).signum();
-                        let intercept_sensor = sign * reading_a.sensor.0 + reading_a.sensor.1;
-                        for i in 0..gap {
-                            let offset = delta_y.signum() * (reading_a.dist() + i + 1);
-                            if sign > 0 {
-                                positive_slope_intercepts.insert(intercept_sensor + offset);
-                            } else {
-                                negative_slope_intercepts.insert(intercept_sensor + offset);
-                            }
-                        }
-                    }
-                }
-            }
+        let area = Rect::new((0, 0), (max_coordinate, max_coordinate));
+        let mut cells = self.uncovered_cells(area);
+        let (x, y) = cells
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("every position is covered by a sensor"))?;
+        if let Some(other) = cells.next() {
+            anyhow::bail!("distress beacon could be at {:?} or {:?}", (x, y), other);
         }
+        Ok(4_000_000 * x + y)
+    }
+}
+
+fn rotate(pos: Pos) -> Pos {
+    (pos.0 + pos.1, pos.0 - pos.1)
+}
+
+/// Rectangle with inclusive bounds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Rect {
+    min: Pos,
+    max: Pos,
+}
 
-        for positive_slope_intercept in &positive_slope_intercepts {
-            for negative_slope_intercept in &negative_slope_intercepts {
-                let x = (-negative_slope_intercept + positive_slope_intercept) / 2;
-                let y = -x + positive_slope_intercept;
-                if 0 <= x
-                    && x <= max_coordinate
-                    && 0 <= y
-                    && y <= max_coordinate
-                    && self
-                        .readings
-                        .iter()
-                        .all(|reading| Self::check(reading, (x, y)))
-                {
-                    return Ok(4_000_000 * x + y);
+impl Rect {
+    fn new(min: Pos, max: Pos) -> Self {
+        Self { min, max }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.min.0 > self.max.0 || self.min.1 > self.max.1
+    }
+
+    fn overlaps(&self, other: &Rect) -> bool {
+        self.min.0 <= other.max.0
+            && other.min.0 <= self.max.0
+            && self.min.1 <= other.max.1
+            && other.min.1 <= self.max.1
+    }
+
+    /// Splits the part of `self` outside of `other` into disjoint rectangles.
+    fn subtract(&self, other: &Rect) -> Vec<Rect> {
+        if !self.overlaps(other) {
+            return vec![*self];
+        }
+        let min_0 = self.min.0.max(other.min.0);
+        let max_0 = self.max.0.min(other.max.0);
+        [
+            Rect::new(self.min, (other.min.0 - 1, self.max.1)),
+            Rect::new((other.max.0 + 1, self.min.1), self.max),
+            Rect::new((min_0, self.min.1), (max_0, other.min.1 - 1)),
+            Rect::new((min_0, other.max.1 + 1), (max_0, self.max.1)),
+        ]
+        .into_iter()
+        .filter(|rect| !rect.is_empty())
+        .collect()
+    }
+}
+
+/// Uncovered rectangle in rotated coordinates, restricted to the cells
+/// within `area`.
+#[derive(Clone, Copy, Debug)]
+struct Region {
+    rotated: Rect,
+    area: Rect,
+}
+
+impl Region {
+    /// Range of `v` for the given `u` within the region and the area.
+    fn v_range(&self, u: i64) -> (i64, i64) {
+        let (min, max) = (self.area.min, self.area.max);
+        let mut low = self.rotated.min.1.max(2 * min.0 - u).max(u - 2 * max.1);
+        let high = self.rotated.max.1.min(2 * max.0 - u).min(u - 2 * min.1);
+        if (low - u).rem_euclid(2) != 0 {
+            // u and v must have the same parity to map to a cell.
+            low += 1;
+        }
+        (low, high)
+    }
+
+    /// Range of `u` for which `v_range` may be non-empty. Each bound of
+    /// `v_range` is linear in `u`, so each pair of bounds limits `u` from one
+    /// side.
+    fn u_range(&self) -> (i64, i64) {
+        let (min, max) = (self.area.min, self.area.max);
+        let lows = [(self.rotated.min.1, 0), (2 * min.0, -1), (-2 * max.1, 1)];
+        let highs = [(self.rotated.max.1, 0), (2 * max.0, -1), (-2 * min.1, 1)];
+        let (mut u_min, mut u_max) = (self.rotated.min.0, self.rotated.max.0);
+        for (low_offset, low_slope) in lows {
+            for (high_offset, high_slope) in highs {
+                // low_offset + low_slope * u <= high_offset + high_slope * u
+                let slope: i64 = low_slope - high_slope;
+                let bound = high_offset - low_offset;
+                match slope.signum() {
+                    1 => u_max = u_max.min(bound.div_euclid(slope)),
+                    -1 => u_min = u_min.max(-bound.div_euclid(-slope)),
+                    _ if bound < 0 => return (1, 0),
+                    _ => (),
                 }
             }
         }
-
-        Err(anyhow::anyhow!("special case not accounted for"))
+        (u_min, u_max)
     }
 
-    fn check(reading: &Reading, pos: Pos) -> bool {
-        reading.dist() < manhatten(reading.sensor, pos)
+    fn cells(self) -> impl Iterator<Item = Pos> {
+        let (u_min, u_max) = self.u_range();
+        (u_min..=u_max).flat_map(move |u| {
+            let (v_min, v_max) = self.v_range(u);
+            (v_min..=v_max)
+                .step_by(2)
+                .map(move |v| ((u + v) / 2, (u - v) / 2))
+        })
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use rstest::rstest;
 
     #[test]
     fn test_example_part1() {
@@ -209,4 +283,68 @@ mod tests {
         let solver = Solver::new(input).unwrap();
         assert_eq!(solver.tuning_frequency(20).unwrap(), 56000011);
     }
+
+    #[rstest]
+    #[case(1000, 0)]
+    #[case(-1, 1)]
+    #[case(0, 3)]
+    #[case(1, 0)]
+    fn test_count_sparse_rows(#[case] row: i64, #[case] expected: usize) {
+        let solver = Solver::new("Sensor at x=0, y=0: closest beacon is at x=0, y=1\n").unwrap();
+        assert_eq!(
+            solver.count_non_beacon_fields_in_row(row).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_hidden_beacon_on_border() {
+        let solver = Solver::new("Sensor at x=2, y=2: closest beacon is at x=2, y=5\n").unwrap();
+        assert_eq!(solver.tuning_frequency(2).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_axis_aligned_sensors() {
+        let solver = Solver::new(
+            "Sensor at x=0, y=2: closest beacon is at x=0, y=3\n\
+             Sensor at x=4, y=2: closest beacon is at x=4, y=1\n",
+        )
+        .unwrap();
+        let cells: Vec<Pos> = solver.uncovered_cells(Rect::new((0, 2), (4, 2))).collect();
+        assert_eq!(cells, vec![(2, 2)]);
+    }
+
+    #[test]
+    fn test_all_uncovered_cells() {
+        let solver = Solver::new("Sensor at x=2, y=2: closest beacon is at x=2, y=4\n").unwrap();
+        let mut cells: Vec<Pos> = solver.uncovered_cells(Rect::new((0, 0), (4, 4))).collect();
+        cells.sort();
+        assert_eq!(
+            cells,
+            vec![
+                (0, 0),
+                (0, 1),
+                (0, 3),
+                (0, 4),
+                (1, 0),
+                (1, 4),
+                (3, 0),
+                (3, 4),
+                (4, 0),
+                (4, 1),
+                (4, 3),
+                (4, 4)
+            ]
+        );
+        assert!(solver.tuning_frequency(4).is_err());
+    }
+
+    #[test]
+    fn test_no_sensors() {
+        let solver = Solver::new("").unwrap();
+        assert_eq!(
+            solver.uncovered_cells(Rect::new((-1, -1), (1, 1))).count(),
+            9
+        );
+    }
 }
@@ -1,13 +1,18 @@
-use std::collections::{BTreeSet, HashMap};
+use std::{collections::HashMap, fmt::Display};
 
 use crate::solvers::error::InputParseError;
 use lazy_static::lazy_static;
 
-use super::{base::AocSolver, day01::TopK};
+use super::base::AocSolver;
 use regex::Regex;
 
 type NodeId = u8;
 
+/// Maximum number of valves with a non-zero flow rate. The search keeps a
+/// table with an entry for every subset of these valves, which takes 8 MiB
+/// at this limit and doubles with every additional valve.
+const MAX_VALVES: usize = 20;
+
 #[derive(Copy, Clone, Debug)]
 struct Node {
     flow_rate: u64,
@@ -61,6 +66,138 @@ impl Graph {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Move,
+    Open,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScheduledAction {
+    pub actor: usize,
+    /// First minute (1-based) occupied by the action.
+    pub start: usize,
+    /// Last minute occupied by the action.
+    pub end: usize,
+    pub action: Action,
+    pub valve: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schedule {
+    pub actions: Vec<ScheduledAction>,
+    /// Pressure released during each minute, starting with minute 1.
+    pub pressure_per_minute: Vec<u64>,
+}
+
+impl Schedule {
+    pub fn total_pressure(&self) -> u64 {
+        self.cumulative_pressure()
+            .last()
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn cumulative_pressure(&self) -> Vec<u64> {
+        self.pressure_per_minute
+            .iter()
+            .scan(0, |total, pressure| {
+                *total += pressure;
+                Some(*total)
+            })
+            .collect()
+    }
+}
+
+fn actor_name(actor: usize) -> String {
+    match actor {
+        0 => "You".into(),
+        1 => "The elephant".into(),
+        n => format!("Elephant {}", n),
+    }
+}
+
+impl Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, pressure) in self.pressure_per_minute.iter().enumerate() {
+            let minute = i + 1;
+            if minute > 1 {
+                writeln!(f)?;
+            }
+            writeln!(f, "== Minute {} ==", minute)?;
+
+            let mut open_valves: Vec<&str> = self
+                .actions
+                .iter()
+                .filter(|a| a.action == Action::Open && a.end < minute)
+                .map(|a| a.valve.as_str())
+                .collect();
+            open_valves.sort_unstable();
+            match open_valves.as_slice() {
+                [] => writeln!(f, "No valves are open.")?,
+                [valve] => writeln!(
+                    f,
+                    "Valve {} is open, releasing {} pressure.",
+                    valve, pressure
+                )?,
+                [valves @ .., last] => {
+                    let separator = if valves.len() > 1 { ", and " } else { " and " };
+                    writeln!(
+                        f,
+                        "Valves {}{}{} are open, releasing {} pressure.",
+                        valves.join(", "),
+                        separator,
+                        last,
+                        pressure
+                    )?
+                }
+            }
+
+            for action in self
+                .actions
+                .iter()
+                .filter(|a| a.start <= minute && minute <= a.end)
+            {
+                let actor = actor_name(action.actor);
+                let third_person = action.actor > 0;
+                match action.action {
+                    Action::Open => writeln!(
+                        f,
+                        "{} {} valve {}.",
+                        actor,
+                        if third_person { "opens" } else { "open" },
+                        action.valve
+                    )?,
+                    Action::Move if minute < action.end => writeln!(
+                        f,
+                        "{} {} moving to valve {}.",
+                        actor,
+                        if third_person { "is" } else { "are" },
+                        action.valve
+                    )?,
+                    Action::Move => writeln!(
+                        f,
+                        "{} {} to valve {}.",
+                        actor,
+                        if third_person { "moves" } else { "move" },
+                        action.valve
+                    )?,
+                }
+            }
+        }
+        writeln!(f, "\nTotal pressure released: {}", self.total_pressure())
+    }
+}
+
+/// Step in a plan as reconstructed by the solver.
+struct PlanStep {
+    actor: usize,
+    steps_left: usize,
+    duration: usize,
+    action: Action,
+    node_id: NodeId,
+}
+
 struct NodeIdMap(HashMap<[u8; 2], u8>);
 
 impl NodeIdMap {
@@ -77,10 +214,19 @@ impl NodeIdMap {
             idx
         }
     }
+
+    fn names(&self) -> Vec<String> {
+        let mut names = vec![String::new(); self.0.len()];
+        for (name, &idx) in self.0.iter() {
+            names[idx as usize] = String::from_utf8_lossy(name).into_owned();
+        }
+        names
+    }
 }
 
 pub struct Solver {
     nodes: Vec<Node>,
+    names: Vec<String>,
     graph: Graph,
     start: NodeId,
 }
@@ -117,6 +263,13 @@ impl<'a> AocSolver<'a, u64, u64> for Solver {
                 graph.add_edge(1, node_id, node_ids.convert([to[0], to[1]]));
             }
         }
+        let num_valves = nodes.values().filter(|node| node.flow_rate > 0).count();
+        if num_valves > MAX_VALVES {
+            return Err(InputParseError::new(format!(
+                "at most {MAX_VALVES} valves with non-zero flow rate are supported, got {num_valves}"
+            ))
+            .into());
+        }
         let start = node_ids.convert([b'A', b'A']);
         for (node_id, node) in nodes.iter() {
             if *node_id != start && node.flow_rate == 0 {
@@ -127,239 +280,291 @@ impl<'a> AocSolver<'a, u64, u64> for Solver {
             nodes: (0..nodes.len())
                 .map(|i| *nodes.get(&(i as u8)).unwrap())
                 .collect(),
+            names: node_ids.names(),
             graph,
             start,
         })
     }
 
     fn solve_part1(&self) -> anyhow::Result<u64> {
-        let mut dp_max_flow = DpMaxFlow::new(&self.nodes, &self.graph);
-        let result = dp_max_flow.max_flow(30, self.start, &mut BTreeSet::new());
-
-        Ok(result)
+        Ok(self.max_flow(30, 1))
     }
 
     fn solve_part2(&self) -> anyhow::Result<Option<u64>> {
-        let mut dp_max_flow = DpMaxFlowElephant::new(&self.nodes, &self.graph);
-        let result = dp_max_flow.max_flow(26, 26, self.start, self.start, 0);
-        Ok(Some(result))
+        Ok(Some(self.max_flow(26, 2)))
     }
-}
 
-struct DpMaxFlow<'a> {
-    cache: HashMap<(usize, NodeId, BTreeSet<NodeId>), u64>,
-    nodes: &'a [Node],
-    graph: &'a Graph,
-    num_non_zero_flow_rates: usize,
+    fn visualize(&self) -> anyhow::Result<Option<String>> {
+        Ok(Some(format!(
+            "{}\n{}",
+            self.schedule(30, 1),
+            self.schedule(26, 2)
+        )))
+    }
 }
 
-impl<'a> DpMaxFlow<'a> {
-    fn new(nodes: &'a [Node], graph: &'a Graph) -> Self {
-        Self {
-            cache: HashMap::new(),
-            nodes,
-            graph,
-            num_non_zero_flow_rates: nodes.iter().filter(|node| node.flow_rate > 0).count(),
-        }
+impl Solver {
+    /// Maximum pressure that `num_actors` actors can release together within
+    /// `time_limit` minutes.
+    pub fn max_flow(&self, time_limit: usize, num_actors: usize) -> u64 {
+        MultiActorMaxFlow::new(&self.nodes, &self.graph, self.start)
+            .max_flow(time_limit, num_actors)
     }
 
-    pub fn max_flow(
-        &mut self,
-        steps_left: usize,
-        node_id: NodeId,
-        opened_valves: &mut BTreeSet<NodeId>,
-    ) -> u64 {
-        if steps_left <= 1 {
-            return 0;
-        }
-        if opened_valves.len() >= self.num_non_zero_flow_rates {
-            return 0;
-        }
-
-        let cache_key = (steps_left, node_id, opened_valves.clone());
-        if let Some(&result) = self.cache.get(&cache_key) {
-            return result;
-        }
-
-        let mut best = TopK::<u64, 1>::new();
+    pub fn schedule(&self, time_limit: usize, num_actors: usize) -> Schedule {
+        let plan = MultiActorMaxFlow::new(&self.nodes, &self.graph, self.start)
+            .plan(time_limit, num_actors);
+        self.to_schedule(time_limit, plan)
+    }
 
-        let node = &self.nodes[node_id as usize];
-        if node.flow_rate > 0 && !opened_valves.contains(&node_id) {
-            opened_valves.insert(node_id);
-            best.push(
-                node.flow_rate * (steps_left - 1) as u64
-                    + self.max_flow(steps_left - 1, node_id, opened_valves),
-            );
-            opened_valves.remove(&node_id);
-        }
+    fn to_schedule(&self, time_limit: usize, plan: Vec<PlanStep>) -> Schedule {
+        let mut pressure_per_minute = vec![0; time_limit];
+        let mut actions: Vec<ScheduledAction> = plan
+            .into_iter()
+            .map(|step| {
+                let end = time_limit - step.steps_left + step.duration;
+                if step.action == Action::Open {
+                    for pressure in &mut pressure_per_minute[end..] {
+                        *pressure += self.nodes[step.node_id as usize].flow_rate;
+                    }
+                }
+                ScheduledAction {
+                    actor: step.actor,
+                    start: time_limit - step.steps_left + 1,
+                    end,
+                    action: step.action,
+                    valve: self.names[step.node_id as usize].clone(),
+                }
+            })
+            .collect();
+        actions.sort_by_key(|a| (a.start, a.actor));
 
-        for &(dist, neighbour) in self.graph.neighbours(node_id) {
-            if (dist as usize) < steps_left {
-                best.push(self.max_flow(steps_left - dist as usize, neighbour, opened_valves))
-            }
+        Schedule {
+            actions,
+            pressure_per_minute,
         }
-
-        let result = best.peek().copied().unwrap_or_default();
-        self.cache.insert(cache_key, result);
-        result
     }
 }
 
-struct DpMaxFlowElephant<'a> {
-    cache: HashMap<(usize, usize, NodeId, NodeId, u32), u64>,
+/// Maximum pressure release for any number of actors working in parallel.
+///
+/// For a single actor, the best pressure release is determined for every
+/// subset of valves. Multiple actors then correspond to combining disjoint
+/// subsets.
+struct MultiActorMaxFlow<'a> {
     nodes: &'a [Node],
-    graph: &'a Graph,
-    num_non_zero_flow_rates: usize,
+    /// Node ids of all valves with a non-zero flow rate. The position in this
+    /// list determines the bit used in valve subset masks.
+    valves: Vec<NodeId>,
+    /// Pairwise distances between the valves with the start node appended
+    /// as the last entry.
+    distances: Vec<Vec<usize>>,
 }
 
-impl<'a> DpMaxFlowElephant<'a> {
-    fn new(nodes: &'a [Node], graph: &'a Graph) -> Self {
+impl<'a> MultiActorMaxFlow<'a> {
+    fn new(nodes: &'a [Node], graph: &Graph, start: NodeId) -> Self {
+        let mut all_distances = vec![vec![usize::MAX; nodes.len()]; nodes.len()];
+        for (from, distances) in all_distances.iter_mut().enumerate() {
+            distances[from] = 0;
+            for &(dist, to) in graph.neighbours(from as NodeId) {
+                distances[to as usize] = distances[to as usize].min(dist as usize);
+            }
+        }
+        for via in 0..nodes.len() {
+            for from in 0..nodes.len() {
+                for to in 0..nodes.len() {
+                    let dist = all_distances[from][via].saturating_add(all_distances[via][to]);
+                    if dist < all_distances[from][to] {
+                        all_distances[from][to] = dist;
+                    }
+                }
+            }
+        }
+
+        let valves: Vec<NodeId> = (0..nodes.len() as NodeId)
+            .filter(|&node_id| nodes[node_id as usize].flow_rate > 0)
+            .collect();
+        let relevant: Vec<usize> = valves
+            .iter()
+            .chain(std::iter::once(&start))
+            .map(|&node_id| node_id as usize)
+            .collect();
+        let distances = relevant
+            .iter()
+            .map(|&from| relevant.iter().map(|&to| all_distances[from][to]).collect())
+            .collect();
+
         Self {
-            cache: HashMap::new(),
             nodes,
-            graph,
-            num_non_zero_flow_rates: nodes.iter().filter(|node| node.flow_rate > 0).count(),
+            valves,
+            distances,
         }
     }
 
-    pub fn max_flow(
-        &mut self,
-        steps_left: usize,
-        steps_left_elephant: usize,
-        node_id: NodeId,
-        elephant_node_id: NodeId,
-        opened_valves: u32,
-    ) -> u64 {
-        if steps_left <= 1 && steps_left_elephant <= 1 {
-            return 0;
-        }
-        if opened_valves.count_ones() as usize >= self.num_non_zero_flow_rates {
-            return 0;
-        }
+    fn start(&self) -> usize {
+        self.valves.len()
+    }
 
-        let cache_key = if steps_left <= steps_left_elephant {
-            (
-                steps_left,
-                steps_left_elephant,
-                node_id,
-                elephant_node_id,
-                opened_valves,
-            )
-        } else {
-            (
-                steps_left_elephant,
-                steps_left,
-                elephant_node_id,
-                node_id,
-                opened_valves,
-            )
-        };
-        if let Some(&result) = self.cache.get(&cache_key) {
-            return result;
+    fn flow_rate(&self, valve: usize) -> u64 {
+        self.nodes[self.valves[valve] as usize].flow_rate
+    }
+
+    pub fn max_flow(&self, steps_left: usize, num_actors: usize) -> u64 {
+        let (best, _) = self.combine_actors(steps_left, num_actors);
+        best
+    }
+
+    pub fn plan(&self, steps_left: usize, num_actors: usize) -> Vec<PlanStep> {
+        let (_, splits) = self.combine_actors(steps_left, num_actors);
+        let mut plan = vec![];
+        let mut remaining = self.all_valves();
+        for (actor, split) in splits.iter().enumerate().rev() {
+            let assigned = split[remaining as usize];
+            self.plan_single_actor(actor, steps_left, assigned, &mut plan);
+            remaining ^= assigned;
         }
+        self.plan_single_actor(splits.len(), steps_left, remaining, &mut plan);
+        plan
+    }
 
-        let best = if steps_left <= steps_left_elephant && steps_left > 1 {
-            self.step_human(
-                steps_left,
-                steps_left_elephant,
-                node_id,
-                elephant_node_id,
-                opened_valves,
-            )
-        } else if steps_left_elephant > 1 {
-            self.step_elephant(
-                steps_left,
-                steps_left_elephant,
-                node_id,
-                elephant_node_id,
-                opened_valves,
-            )
-        } else {
-            0
-        };
+    fn all_valves(&self) -> u32 {
+        u32::MAX
+            .checked_shr(u32::BITS - self.valves.len() as u32)
+            .unwrap_or(0)
+    }
 
-        self.cache.insert(cache_key, best);
+    /// Best pressure release for each subset of valves, where the subset
+    /// limits the valves that may be opened.
+    fn best_per_subset(&self, steps_left: usize) -> Vec<u64> {
+        let mut best = vec![0; 1 << self.valves.len()];
+        self.explore(self.start(), steps_left, 0, 0, &mut best);
+        for valve in 0..self.valves.len() {
+            for subset in 0..best.len() {
+                if subset & (1 << valve) != 0 {
+                    best[subset] = best[subset].max(best[subset ^ (1 << valve)]);
+                }
+            }
+        }
         best
     }
 
-    fn step_human(
-        &mut self,
+    fn explore(
+        &self,
+        position: usize,
         steps_left: usize,
-        steps_left_elephant: usize,
-        node_id: NodeId,
-        elephant_node_id: NodeId,
         opened_valves: u32,
-    ) -> u64 {
-        let mut best = TopK::<u64, 1>::new();
-
-        let node = &self.nodes[node_id as usize];
-        if node.flow_rate > 0 && opened_valves & (1 << node_id) == 0 {
-            let opened_valves = opened_valves | (1 << node_id);
-            best.push(
-                node.flow_rate * (steps_left - 1) as u64
-                    + self.max_flow(
-                        steps_left - 1,
-                        steps_left_elephant,
-                        node_id,
-                        elephant_node_id,
-                        opened_valves,
-                    ),
-            );
+        released: u64,
+        best: &mut [u64],
+    ) {
+        best[opened_valves as usize] = best[opened_valves as usize].max(released);
+        for valve in 0..self.valves.len() {
+            let dist = self.distances[position][valve];
+            if opened_valves & (1 << valve) == 0 && dist + 1 < steps_left {
+                let steps_left = steps_left - dist - 1;
+                self.explore(
+                    valve,
+                    steps_left,
+                    opened_valves | (1 << valve),
+                    released + self.flow_rate(valve) * steps_left as u64,
+                    best,
+                );
+            }
         }
+    }
 
-        for &(dist, neighbour) in self.graph.neighbours(node_id) {
-            if (dist as usize) < steps_left {
-                best.push(self.max_flow(
-                    steps_left - dist as usize,
-                    steps_left_elephant,
-                    neighbour,
-                    elephant_node_id,
-                    opened_valves,
-                ));
+    /// Combines the single actor results into the best pressure release for
+    /// `num_actors` actors opening any of the valves. Also returns, for each
+    /// added actor, the subset of valves assigned to that actor.
+    ///
+    /// Intermediate actors are combined for every valve subset, but the last
+    /// one only for the subset of all valves.
+    fn combine_actors(&self, steps_left: usize, num_actors: usize) -> (u64, Vec<Vec<u32>>) {
+        let all_valves = self.all_valves() as usize;
+        let single = self.best_per_subset(steps_left);
+        let mut best = single.clone();
+        let mut splits = vec![];
+        for actor in 1..num_actors {
+            let subsets = if actor + 1 == num_actors {
+                all_valves..=all_valves
+            } else {
+                0..=all_valves
+            };
+            let mut combined = best.clone();
+            let mut split = vec![0; best.len()];
+            for subset in subsets {
+                let mut assigned = subset;
+                loop {
+                    let released = single[assigned] + best[subset ^ assigned];
+                    if released > combined[subset] {
+                        combined[subset] = released;
+                        split[subset] = assigned as u32;
+                    }
+                    if assigned == 0 {
+                        break;
+                    }
+                    assigned = (assigned - 1) & subset;
+                }
             }
+            best = combined;
+            splits.push(split);
         }
-
-        best.peek().copied().unwrap_or_default()
+        (best[all_valves], splits)
     }
 
-    fn step_elephant(
-        &mut self,
+    fn plan_single_actor(
+        &self,
+        actor: usize,
         steps_left: usize,
-        steps_left_elephant: usize,
-        node_id: NodeId,
-        elephant_node_id: NodeId,
-        opened_valves: u32,
-    ) -> u64 {
-        let mut best = TopK::<u64, 1>::new();
-
-        let node = &self.nodes[elephant_node_id as usize];
-        if node.flow_rate > 0 && opened_valves & (1 << elephant_node_id) == 0 {
-            let opened_valves = opened_valves | (1 << elephant_node_id);
-            best.push(
-                node.flow_rate * (steps_left_elephant - 1) as u64
-                    + self.max_flow(
-                        steps_left,
-                        steps_left_elephant - 1,
-                        node_id,
-                        elephant_node_id,
-                        opened_valves,
-                    ),
-            );
-        }
-
-        for &(dist, neighbour) in self.graph.neighbours(elephant_node_id) {
-            if (dist as usize) < steps_left_elephant {
-                best.push(self.max_flow(
+        allowed_valves: u32,
+        plan: &mut Vec<PlanStep>,
+    ) {
+        let (_, order) = self.best_order(self.start(), steps_left, allowed_valves);
+        let mut position = self.start();
+        let mut steps_left = steps_left;
+        for valve in order {
+            let dist = self.distances[position][valve];
+            if dist > 0 {
+                plan.push(PlanStep {
+                    actor,
                     steps_left,
-                    steps_left_elephant - dist as usize,
-                    node_id,
-                    neighbour,
-                    opened_valves,
-                ));
+                    duration: dist,
+                    action: Action::Move,
+                    node_id: self.valves[valve],
+                });
             }
+            plan.push(PlanStep {
+                actor,
+                steps_left: steps_left - dist,
+                duration: 1,
+                action: Action::Open,
+                node_id: self.valves[valve],
+            });
+            position = valve;
+            steps_left -= dist + 1;
         }
+    }
 
-        best.peek().copied().unwrap_or_default()
+    fn best_order(
+        &self,
+        position: usize,
+        steps_left: usize,
+        allowed_valves: u32,
+    ) -> (u64, Vec<usize>) {
+        let mut best = (0, vec![]);
+        for valve in 0..self.valves.len() {
+            let dist = self.distances[position][valve];
+            if allowed_valves & (1 << valve) != 0 && dist + 1 < steps_left {
+                let steps_left = steps_left - dist - 1;
+                let (released, mut order) =
+                    self.best_order(valve, steps_left, allowed_valves ^ (1 << valve));
+                let released = released + self.flow_rate(valve) * steps_left as u64;
+                if released > best.0 {
+                    order.insert(0, valve);
+                    best = (released, order);
+                }
+            }
+        }
+        best
     }
 }
 
@@ -367,10 +572,147 @@ impl<'a> DpMaxFlowElephant<'a> {
 mod tests {
     use super::*;
     use crate::solvers::test::test_example_input;
+    use rstest::rstest;
 
     #[test]
     fn test_example() {
         let input = include_str!("examples/day16");
         test_example_input::<Solver, _, _>(input, 1651, Some(1707));
     }
+
+    #[rstest]
+    #[case(30, 1, 1651)]
+    #[case(26, 1, 1327)]
+    #[case(26, 2, 1707)]
+    #[case(26, 3, 1794)]
+    #[case(10, 3, 498)]
+    fn test_max_flow(#[case] time_limit: usize, #[case] num_actors: usize, #[case] expected: u64) {
+        let input = include_str!("examples/day16");
+        let solver = Solver::new(input).unwrap();
+        assert_eq!(solver.max_flow(time_limit, num_actors), expected);
+        assert_eq!(
+            solver.schedule(time_limit, num_actors).total_pressure(),
+            expected
+        );
+    }
+
+    /// Valves with flow rates 1 to `num_valves`, each only connected to the
+    /// start.
+    fn star_input(num_valves: usize) -> String {
+        let names: Vec<String> = (0..num_valves)
+            .map(|i| {
+                format!(
+                    "{}{}",
+                    (b'B' + (i / 26) as u8) as char,
+                    (b'A' + (i % 26) as u8) as char
+                )
+            })
+            .collect();
+        let mut input = format!(
+            "Valve AA has flow rate=0; tunnels lead to valves {}\n",
+            names.join(", ")
+        );
+        for (i, name) in names.iter().enumerate() {
+            input.push_str(&format!(
+                "Valve {} has flow rate={}; tunnel leads to valve AA\n",
+                name,
+                i + 1
+            ));
+        }
+        input
+    }
+
+    #[test]
+    fn test_valve_limit() {
+        let solver = Solver::new(&star_input(MAX_VALVES)).unwrap();
+        let top = MAX_VALVES as u64;
+        assert_eq!(solver.max_flow(6, 1), 4 * top + (top - 1));
+        let expected = 4 * top + 4 * (top - 1) + (top - 2) + (top - 3);
+        assert_eq!(solver.max_flow(6, 2), expected);
+        assert_eq!(solver.schedule(6, 2).total_pressure(), expected);
+
+        assert!(Solver::new(&star_input(MAX_VALVES + 1)).is_err());
+    }
+
+    #[test]
+    fn test_schedule() {
+        let input = include_str!("examples/day16");
+        let solver = Solver::new(input).unwrap();
+
+        let schedule = solver.schedule(30, 1);
+        assert_eq!(schedule.total_pressure(), 1651);
+        assert_eq!(schedule.cumulative_pressure().last(), Some(&1651));
+        assert_eq!(schedule.pressure_per_minute[29], 81);
+        let opened: Vec<_> = schedule
+            .actions
+            .iter()
+            .filter(|a| a.action == Action::Open)
+            .map(|a| (a.end, a.valve.as_str()))
+            .collect();
+        assert_eq!(
+            opened,
+            vec![
+                (2, "DD"),
+                (5, "BB"),
+                (9, "JJ"),
+                (17, "HH"),
+                (21, "EE"),
+                (24, "CC")
+            ]
+        );
+
+        let schedule = solver.schedule(26, 2);
+        assert_eq!(schedule.total_pressure(), 1707);
+        assert!(schedule.actions.iter().any(|a| a.actor == 1));
+    }
+
+    #[test]
+    fn test_schedule_display() {
+        let schedule = Schedule {
+            actions: vec![
+                ScheduledAction {
+                    actor: 0,
+                    start: 1,
+                    end: 2,
+                    action: Action::Move,
+                    valve: "BB".into(),
+                },
+                ScheduledAction {
+                    actor: 1,
+                    start: 1,
+                    end: 1,
+                    action: Action::Open,
+                    valve: "AA".into(),
+                },
+                ScheduledAction {
+                    actor: 0,
+                    start: 3,
+                    end: 3,
+                    action: Action::Open,
+                    valve: "BB".into(),
+                },
+            ],
+            pressure_per_minute: vec![0, 1, 1, 3],
+        };
+        assert_eq!(
+            schedule.to_string(),
+            "== Minute 1 ==\n\
+             No valves are open.\n\
+             You are moving to valve BB.\n\
+             The elephant opens valve AA.\n\
+             \n\
+             == Minute 2 ==\n\
+             Valve AA is open, releasing 1 pressure.\n\
+             You move to valve BB.\n\
+             \n\
+             == Minute 3 ==\n\
+             Valve AA is open, releasing 1 pressure.\n\
+             You open valve BB.\n\
+             \n\
+             == Minute 4 ==\n\
+             Valves AA and BB are open, releasing 3 pressure.\n\
+             \n\
+             Total pressure released: 5\n"
+        );
+    }
 }
@@ -1,64 +1,161 @@
-use std::fmt::{Display, Write};
+use std::{
+    collections::HashMap,
+    fmt::{Display, Write},
+};
 
-use super::base::AocSolver;
+use super::{base::AocSolver, error::InputParseError};
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-enum RockShape {
-    Minus,
-    Plus,
-    FlippedL,
-    I,
-    Square,
+/// Rock shapes in the order they fall, as in the puzzle description.
+pub const DEFAULT_ROCK_SHAPES: &str = "\
+####
+
+.#.
+###
+.#.
+
+..#
+..#
+###
+
+#
+#
+#
+#
+
+##
+##
+";
+
+pub const CHAMBER_WIDTH: usize = 7;
+/// Chamber rows are stored as bit masks, which limits the width.
+const MAX_CHAMBER_WIDTH: usize = u64::BITS as usize;
+/// Deepest surface profile used to detect a cycle.
+const MAX_PROFILE_DEPTH: usize = 64;
+/// Number of rocks dropped before giving up on finding a cycle.
+const MAX_CYCLE_SEARCH_ROCKS: usize = 100_000;
+
+/// Rock shape as bit masks per row, bottom row first. Bit `i` corresponds to
+/// the `i`-th column from the left.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct RockShape {
+    rows: Vec<u64>,
+    width: usize,
 }
 
-struct RockShapeIterator(u8);
-
-impl Iterator for RockShapeIterator {
-    type Item = RockShape;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        self.0 += 1;
-        Some(match self.0 {
-            1 => RockShape::Minus,
-            2 => RockShape::Plus,
-            3 => RockShape::FlippedL,
-            4 => RockShape::I,
-            5 => {
-                self.0 = 0;
-                RockShape::Square
+impl RockShape {
+    /// Parses shapes drawn with `#` and `.`, top row first, separated by empty
+    /// lines.
+    pub fn parse_set(definition: &str) -> Result<Vec<Self>, InputParseError> {
+        let mut shapes = vec![];
+        let mut lines = definition.lines().map(str::trim).peekable();
+        while lines.peek().is_some() {
+            let drawing: Vec<&str> = lines
+                .by_ref()
+                .skip_while(|line| line.is_empty())
+                .take_while(|line| !line.is_empty())
+                .collect();
+            if !drawing.is_empty() {
+                shapes.push(Self::parse(&drawing)?);
             }
-            _ => panic!("should not happen"),
-        })
+        }
+        if shapes.is_empty() {
+            return Err(InputParseError::new("no rock shapes defined".into()));
+        }
+        Ok(shapes)
+    }
+
+    fn parse(drawing: &[&str]) -> Result<Self, InputParseError> {
+        let mut rows = vec![];
+        let mut width = 0;
+        for line in drawing.iter().rev() {
+            if line.len() > MAX_CHAMBER_WIDTH {
+                return Err(InputParseError::new(format!(
+                    "rock shapes can be at most {} wide",
+                    MAX_CHAMBER_WIDTH
+                )));
+            }
+            let mut row = 0;
+            for (i, c) in line.chars().enumerate() {
+                match c {
+                    '#' => {
+                        row |= 1 << i;
+                        width = width.max(i + 1);
+                    }
+                    '.' => (),
+                    c => {
+                        return Err(InputParseError::new(format!(
+                            "invalid character '{}' in rock shape",
+                            c
+                        )))
+                    }
+                }
+            }
+            rows.push(row);
+        }
+        if width == 0 {
+            return Err(InputParseError::new("empty rock shape".into()));
+        }
+        if rows.first() == Some(&0) || rows.last() == Some(&0) {
+            return Err(InputParseError::new(
+                "rock shapes must not have empty rows at the top or bottom".into(),
+            ));
+        }
+        if rows.iter().fold(0, |acc, row| acc | row) & 1 == 0 {
+            return Err(InputParseError::new(
+                "rock shapes must not have an empty column on the left".into(),
+            ));
+        }
+        Ok(Self { rows, width })
     }
-}
 
-impl RockShapeIterator {
-    fn new() -> Self {
-        Self(0)
+    fn height(&self) -> usize {
+        self.rows.len()
     }
 }
 
-struct Tetris<const WIDTH: usize, JP>
-where
-    JP: Iterator<Item = i8>,
-{
-    chamber: Vec<[bool; WIDTH]>,
-    jet_pattern: JP,
-    rock_shapes: RockShapeIterator,
+/// State of the simulation that determines how the following rocks fall:
+/// index of the next rock shape, index of the next jet and the surface
+/// profile given as the empty cells reachable from above, top row first.
+type TetrisState = (usize, usize, Vec<u64>);
+
+struct Tetris {
+    width: usize,
+    chamber: Vec<u64>,
+    jet_pattern: Vec<i8>,
+    jet_index: usize,
+    rock_shapes: Vec<RockShape>,
+    rock_index: usize,
     tower_height: usize,
 }
 
-impl<const WIDTH: usize, JP> Tetris<WIDTH, JP>
-where
-    JP: Iterator<Item = i8>,
-{
-    pub fn new(jet_pattern: JP) -> Self {
-        Self {
+impl Tetris {
+    pub fn new(
+        width: usize,
+        rock_shapes: Vec<RockShape>,
+        jet_pattern: Vec<i8>,
+    ) -> anyhow::Result<Self> {
+        if jet_pattern.is_empty() {
+            anyhow::bail!("empty jet pattern");
+        }
+        if width > MAX_CHAMBER_WIDTH {
+            anyhow::bail!("chamber can be at most {} wide", MAX_CHAMBER_WIDTH);
+        }
+        if let Some(shape) = rock_shapes.iter().find(|shape| shape.width + 2 > width) {
+            anyhow::bail!(
+                "rock shape of width {} does not fit into chamber of width {}",
+                shape.width,
+                width
+            );
+        }
+        Ok(Self {
+            width,
             chamber: vec![],
             jet_pattern,
-            rock_shapes: RockShapeIterator::new(),
+            jet_index: 0,
+            rock_shapes,
+            rock_index: 0,
             tower_height: 0,
-        }
+        })
     }
 
     pub fn tower_height(&self) -> usize {
@@ -66,116 +163,88 @@ where
     }
 
     pub fn drop_block(&mut self) {
-        let shape = self.rock_shapes.next().unwrap();
+        let shape_index = self.rock_index;
+        self.rock_index = (self.rock_index + 1) % self.rock_shapes.len();
+        let shape = &self.rock_shapes[shape_index];
         let mut bottom = self.tower_height + 3;
         let mut left: isize = 2;
 
-        while self.chamber.len() < self.tower_height + 3 + 4 {
-            self.chamber.push([false; WIDTH]);
+        while self.chamber.len() < self.tower_height + 3 + shape.height() {
+            self.chamber.push(0);
         }
 
         loop {
-            let jet = self.jet_pattern.next().unwrap() as isize;
-            if self.check(shape, bottom, left + jet) {
+            let jet = self.jet_pattern[self.jet_index] as isize;
+            self.jet_index = (self.jet_index + 1) % self.jet_pattern.len();
+            if self.check(shape_index, bottom, left + jet) {
                 left += jet;
             }
 
-            if bottom > 0 && self.check(shape, bottom - 1, left) {
+            if bottom > 0 && self.check(shape_index, bottom - 1, left) {
                 bottom -= 1;
             } else {
-                self.materialize(shape, bottom, left as usize);
+                self.materialize(shape_index, bottom, left as usize);
                 break;
             }
         }
     }
 
-    fn check(&self, shape: RockShape, bottom: usize, left: isize) -> bool {
-        if left < 0 {
-            return false;
-        }
-        let left = left as usize;
-        match shape {
-            RockShape::Minus => {
-                left + 3 < WIDTH && self.chamber[bottom][left..left + 4].iter().all(|f| !f)
-            }
-            RockShape::Plus => {
-                left + 2 < WIDTH
-                    && self.chamber[bottom + 1][left..left + 3].iter().all(|f| !f)
-                    && !self.chamber[bottom][left + 1]
-                    && !self.chamber[bottom + 2][left + 1]
-            }
-            RockShape::FlippedL => {
-                left + 2 < WIDTH
-                    && self.chamber[bottom][left..left + 3].iter().all(|f| !f)
-                    && !self.chamber[bottom + 1][left + 2]
-                    && !self.chamber[bottom + 2][left + 2]
+    /// Falling rocks only move through empty cells reachable from above the
+    /// tower, so these determine the following rocks exactly. Returns `None`
+    /// if they extend deeper than `MAX_PROFILE_DEPTH` above the floor.
+    pub fn state(&self) -> Option<TetrisState> {
+        let all_columns = u64::MAX >> (MAX_CHAMBER_WIDTH - self.width);
+        let mut profile = vec![];
+        let mut reachable = all_columns;
+        for &row in self.chamber[..self.tower_height].iter().rev() {
+            let empty = !row & all_columns;
+            let mut cells = reachable & empty;
+            loop {
+                let spread = (cells | cells << 1 | cells >> 1) & empty;
+                if spread == cells {
+                    break;
+                }
+                cells = spread;
             }
-            RockShape::I => {
-                left < WIDTH
-                    && self.chamber[bottom..bottom + 4]
-                        .iter()
-                        .all(|row| !row[left])
+            if cells == 0 {
+                break;
             }
-            RockShape::Square => {
-                left + 1 < WIDTH
-                    && !self.chamber[bottom][left]
-                    && !self.chamber[bottom][left + 1]
-                    && !self.chamber[bottom + 1][left]
-                    && !self.chamber[bottom + 1][left + 1]
+            if profile.len() == MAX_PROFILE_DEPTH {
+                return None;
             }
+            profile.push(cells);
+            reachable = cells;
         }
+        Some((self.rock_index, self.jet_index, profile))
     }
 
-    fn materialize(&mut self, shape: RockShape, bottom: usize, left: usize) {
-        match shape {
-            RockShape::Minus => {
-                for field in self.chamber[bottom][left..left + 4].iter_mut() {
-                    *field = true;
-                }
-                self.tower_height = self.tower_height.max(bottom + 1);
-            }
-            RockShape::Plus => {
-                self.chamber[bottom + 1][left] = true;
-                self.chamber[bottom + 1][left + 1] = true;
-                self.chamber[bottom + 1][left + 2] = true;
-                self.chamber[bottom][left + 1] = true;
-                self.chamber[bottom + 2][left + 1] = true;
-                self.tower_height = self.tower_height.max(bottom + 3);
-            }
-            RockShape::FlippedL => {
-                self.chamber[bottom][left] = true;
-                self.chamber[bottom][left + 1] = true;
-                self.chamber[bottom][left + 2] = true;
-                self.chamber[bottom + 1][left + 2] = true;
-                self.chamber[bottom + 2][left + 2] = true;
-                self.tower_height = self.tower_height.max(bottom + 3);
-            }
-            RockShape::I => {
-                for row in self.chamber[bottom..bottom + 4].iter_mut() {
-                    row[left] = true;
-                }
-                self.tower_height = self.tower_height.max(bottom + 4);
-            }
-            RockShape::Square => {
-                self.chamber[bottom][left] = true;
-                self.chamber[bottom][left + 1] = true;
-                self.chamber[bottom + 1][left] = true;
-                self.chamber[bottom + 1][left + 1] = true;
-                self.tower_height = self.tower_height.max(bottom + 2);
-            }
+    fn check(&self, shape_index: usize, bottom: usize, left: isize) -> bool {
+        let shape = &self.rock_shapes[shape_index];
+        if left < 0 || left as usize + shape.width > self.width {
+            return false;
         }
+        shape
+            .rows
+            .iter()
+            .zip(&self.chamber[bottom..])
+            .all(|(shape_row, chamber_row)| (shape_row << left) & chamber_row == 0)
+    }
+
+    fn materialize(&mut self, shape_index: usize, bottom: usize, left: usize) {
+        let shape = &self.rock_shapes[shape_index];
+        for (shape_row, chamber_row) in shape.rows.iter().zip(&mut self.chamber[bottom..]) {
+            *chamber_row |= shape_row << left;
+        }
+        self.tower_height = self.tower_height.max(bottom + shape.height());
     }
 }
 
-impl<const WIDTH: usize, JP> Display for Tetris<WIDTH, JP>
-where
-    JP: Iterator<Item = i8>,
-{
+impl Display for Tetris {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         for row in (self.tower_height - 3..self.tower_height).rev() {
             f.write_char('|')?;
-            for cell in self.chamber[row] {
-                if cell {
+            for column in 0..self.width {
+                if self.chamber[row] & (1 << column) != 0 {
                     f.write_char('#')?;
                 } else {
                     f.write_char('.')?;
@@ -184,7 +253,7 @@ where
             f.write_str("|\n")?;
         }
         f.write_char('+')?;
-        for _ in 0..WIDTH {
+        for _ in 0..self.width {
             f.write_char('-')?;
         }
         f.write_str("+\n")?;
@@ -192,83 +261,125 @@ where
     }
 }
 
-pub struct Solver<'a> {
-    input: &'a str,
+/// Repetition in the tower: after `start` rocks, every `length` rocks add
+/// `height_gain` to the tower height.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cycle {
+    pub start: usize,
+    pub length: usize,
+    pub height_gain: usize,
+}
+
+pub struct Solver {
+    width: usize,
+    rock_shapes: Vec<RockShape>,
+    jet_pattern: Vec<i8>,
+    /// Tower height after each number of rocks up to the end of the first
+    /// repetition of the cycle, or the end of the search if there is none.
+    heights: Vec<usize>,
+    cycle: Option<Cycle>,
 }
 
-impl<'a> AocSolver<'a, usize, usize> for Solver<'a> {
+impl<'a> AocSolver<'a, usize, usize> for Solver {
     fn new(input: &'a str) -> anyhow::Result<Self>
     where
         Self: Sized,
     {
-        Ok(Self { input })
+        Self::with_config(input, DEFAULT_ROCK_SHAPES, CHAMBER_WIDTH)
     }
 
     fn solve_part1(&self) -> anyhow::Result<usize> {
-        let mut tetris: Tetris<7, _> = Tetris::new(
-            self.input
-                .as_bytes()
-                .iter()
-                .filter_map(|c| match c {
-                    b'<' => Some(-1),
-                    b'>' => Some(1),
-                    _ => None,
-                })
-                .cycle(),
-        );
-        for _ in 0..2022 {
-            tetris.drop_block();
-        }
-        Ok(tetris.tower_height())
+        self.tower_height_after(2022)
     }
 
     fn solve_part2(&self) -> anyhow::Result<Option<usize>> {
-        Ok(Some(self.find_repeated_cycles()))
+        Ok(Some(self.tower_height_after(1_000_000_000_000)?))
+    }
+
+    fn visualize(&self) -> anyhow::Result<Option<String>> {
+        Ok(Some(match self.cycle {
+            Some(cycle) => format!(
+                "Cycle starts after {} rocks, repeats every {} rocks and adds {} to the height.\n",
+                cycle.start, cycle.length, cycle.height_gain
+            ),
+            None => format!("No cycle found within {} rocks.\n", MAX_CYCLE_SEARCH_ROCKS),
+        }))
     }
 }
 
-impl<'a> Solver<'a> {
-    fn find_repeated_cycles(&self) -> usize {
-        let mut tetris: Tetris<7, _> = Tetris::new(
-            self.input
-                .as_bytes()
-                .iter()
-                .filter_map(|c| match c {
-                    b'<' => Some(-1),
-                    b'>' => Some(1),
-                    _ => None,
-                })
-                .cycle(),
-        );
-        let cycle_size = 5 * self.input.trim().len();
-        let mut heights: Vec<usize> = vec![0];
-        let mut cycle: usize = 0;
-        loop {
-            for _ in 0..cycle_size {
-                tetris.drop_block();
-            }
-            heights.push(tetris.tower_height());
-            for cycles_count in 1..=cycle / 2 {
-                let h1 = heights[heights.len() - 1];
-                let h2 = heights[heights.len() - cycles_count - 1];
-                let h3 = heights[heights.len() - 2 * cycles_count - 1];
-                if h1 - h2 == h2 - h3
-                    && (0..h1 - h2).all(|x| tetris.chamber[h2 + x] == tetris.chamber[h3 + x])
-                {
-                    let rocks_before_start = (cycle - 2 * cycles_count + 1) * cycle_size;
-                    let num_repeats =
-                        (1000000000000 - rocks_before_start) / (cycles_count * cycle_size);
-                    let remaining_rocks =
-                        (1000000000000 - rocks_before_start) % (cycles_count * cycle_size);
-                    let prev_height = tetris.tower_height();
-                    for _ in 0..remaining_rocks {
-                        tetris.drop_block();
-                    }
-                    return h3 + (h2 - h3) * num_repeats + tetris.tower_height() - prev_height;
+impl Solver {
+    /// Solver for rock shapes in the format of `DEFAULT_ROCK_SHAPES` falling
+    /// in a chamber of the given width.
+    pub fn with_config(input: &str, rock_shapes: &str, width: usize) -> anyhow::Result<Self> {
+        let jet_pattern = input
+            .bytes()
+            .filter_map(|c| match c {
+                b'<' => Some(-1),
+                b'>' => Some(1),
+                _ => None,
+            })
+            .collect();
+        let mut solver = Self {
+            width,
+            rock_shapes: RockShape::parse_set(rock_shapes)?,
+            jet_pattern,
+            heights: vec![],
+            cycle: None,
+        };
+        solver.find_cycle()?;
+        Ok(solver)
+    }
+
+    fn tetris(&self) -> anyhow::Result<Tetris> {
+        Tetris::new(
+            self.width,
+            self.rock_shapes.clone(),
+            self.jet_pattern.clone(),
+        )
+    }
+
+    /// Drops rocks until a state repeats or `MAX_CYCLE_SEARCH_ROCKS` rocks
+    /// have fallen.
+    fn find_cycle(&mut self) -> anyhow::Result<()> {
+        let mut tetris = self.tetris()?;
+        let mut seen: HashMap<TetrisState, usize> = HashMap::new();
+        self.heights = vec![0];
+        for rocks in 0..MAX_CYCLE_SEARCH_ROCKS {
+            if let Some(state) = tetris.state() {
+                if let Some(start) = seen.insert(state, rocks) {
+                    self.cycle = Some(Cycle {
+                        start,
+                        length: rocks - start,
+                        height_gain: tetris.tower_height() - self.heights[start],
+                    });
+                    return Ok(());
                 }
             }
-            cycle += 1;
+            tetris.drop_block();
+            self.heights.push(tetris.tower_height());
+        }
+        Ok(())
+    }
+
+    pub fn tower_height_after(&self, rocks: usize) -> anyhow::Result<usize> {
+        if rocks < self.heights.len() {
+            return Ok(self.heights[rocks]);
         }
+        let Some(Cycle {
+            start,
+            length,
+            height_gain,
+        }) = self.cycle
+        else {
+            anyhow::bail!(
+                "no cycle found within {} rocks to extrapolate to {} rocks",
+                MAX_CYCLE_SEARCH_ROCKS,
+                rocks
+            );
+        };
+        let repeats = (rocks - start) / length;
+        let remaining = (rocks - start) % length;
+        Ok(self.heights[start + remaining] + repeats * height_gain)
     }
 }
 
@@ -276,10 +387,126 @@ impl<'a> Solver<'a> {
 mod tests {
     use super::*;
     use crate::solvers::test::test_example_input;
+    use rstest::rstest;
 
     #[test]
     fn test_example() {
         let input = include_str!("examples/day17");
         test_example_input::<Solver, _, _>(input, 3068, Some(1514285714288));
     }
+
+    fn tetris_with(width: usize, shapes: &str, jets: &[i8]) -> anyhow::Result<Tetris> {
+        Tetris::new(width, RockShape::parse_set(shapes)?, jets.to_vec())
+    }
+
+    #[test]
+    fn test_parse_default_shapes() {
+        let shapes = RockShape::parse_set(DEFAULT_ROCK_SHAPES).unwrap();
+        assert_eq!(shapes.len(), 5);
+        assert_eq!(
+            shapes[1],
+            RockShape {
+                rows: vec![0b010, 0b111, 0b010],
+                width: 3
+            }
+        );
+        assert_eq!(
+            shapes[2],
+            RockShape {
+                rows: vec![0b111, 0b100, 0b100],
+                width: 3
+            }
+        );
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("#x#")]
+    #[case(".#\n.#")]
+    #[case("...\n")]
+    #[case("..\n##")]
+    fn test_parse_invalid_shapes(#[case] definition: &str) {
+        assert!(RockShape::parse_set(definition).is_err());
+    }
+
+    #[test]
+    fn test_custom_shapes_and_width() {
+        let mut tetris = tetris_with(9, "#######", &[1, -1, -1]).unwrap();
+        for _ in 0..100 {
+            tetris.drop_block();
+        }
+        assert_eq!(tetris.tower_height(), 100);
+
+        let mut tetris = tetris_with(3, "#\n#\n#\n#", &[-1]).unwrap();
+        for _ in 0..10 {
+            tetris.drop_block();
+        }
+        assert_eq!(tetris.tower_height(), 40);
+
+        let mut tetris = tetris_with(64, &"#".repeat(60), &[1]).unwrap();
+        for _ in 0..5 {
+            tetris.drop_block();
+        }
+        assert_eq!(tetris.tower_height(), 5);
+        assert_eq!(tetris.chamber[0], u64::MAX << 4);
+    }
+
+    #[test]
+    fn test_shape_does_not_fit() {
+        assert!(tetris_with(65, "#", &[1]).is_err());
+        assert!(tetris_with(4, "###", &[1]).is_err());
+    }
+
+    #[test]
+    fn test_cycle() {
+        let solver = Solver::new(include_str!("examples/day17")).unwrap();
+        let cycle = solver.cycle.unwrap();
+        assert_eq!(cycle.length, 35);
+        assert_eq!(cycle.height_gain, 53);
+
+        let mut tetris = solver.tetris().unwrap();
+        for rocks in 0..=3000 {
+            assert_eq!(
+                solver.tower_height_after(rocks).unwrap(),
+                tetris.tower_height()
+            );
+            tetris.drop_block();
+        }
+    }
+
+    #[test]
+    fn test_with_config() {
+        let input = include_str!("examples/day17");
+        let solver = Solver::with_config(input, DEFAULT_ROCK_SHAPES, CHAMBER_WIDTH).unwrap();
+        assert_eq!(solver.solve_part1().unwrap(), 3068);
+
+        let solver = Solver::with_config(input, "###\n\n.#\n##", 9).unwrap();
+        let mut tetris = solver.tetris().unwrap();
+        for rocks in 0..=3000 {
+            assert_eq!(
+                solver.tower_height_after(rocks).unwrap(),
+                tetris.tower_height()
+            );
+            tetris.drop_block();
+        }
+
+        assert!(Solver::with_config(input, "#x#", CHAMBER_WIDTH).is_err());
+        assert!(Solver::with_config(input, DEFAULT_ROCK_SHAPES, 5).is_err());
+    }
+
+    #[test]
+    fn test_no_cycle_with_empty_column() {
+        // The jets push every rock against the right wall, so the leftmost
+        // columns stay empty down to the floor.
+        let solver = Solver::with_config(">", "####", CHAMBER_WIDTH).unwrap();
+        assert_eq!(solver.cycle, None);
+        assert_eq!(solver.solve_part1().unwrap(), 2022);
+        assert!(solver.solve_part2().is_err());
+        assert!(solver.visualize().unwrap().unwrap().starts_with("No cycle"));
+    }
+
+    #[test]
+    fn test_empty_jet_pattern() {
+        assert!(Solver::new("").is_err());
+    }
 }
@@ -1,4 +1,7 @@
-use std::{collections::VecDeque, num::ParseIntError};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
 
 use super::{base::AocSolver, error::InputParseError};
 
@@ -9,7 +12,203 @@ enum Classification {
     TrappedAir,
 }
 
+type Voxel = [i64; 3];
+
+/// Face of a lava voxel pointing along `axis` in positive or negative
+/// direction.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Face {
+    voxel: Voxel,
+    axis: usize,
+    positive: bool,
+}
+
+fn neighbour(voxel: &Voxel, axis: usize, positive: bool) -> Voxel {
+    let mut neighbour = *voxel;
+    neighbour[axis] += if positive { 1 } else { -1 };
+    neighbour
+}
+
+fn face_neighbours(voxel: &Voxel) -> impl Iterator<Item = Voxel> + '_ {
+    (0..3).flat_map(move |axis| [false, true].map(|positive| neighbour(voxel, axis, positive)))
+}
+
+/// Neighbours sharing a face, an edge or a corner.
+fn all_neighbours(voxel: &Voxel) -> impl Iterator<Item = Voxel> + '_ {
+    (-1..=1)
+        .flat_map(|dx| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dz| [dx, dy, dz])))
+        .filter(|&offset| offset != [0, 0, 0])
+        .map(move |offset| [0, 1, 2].map(|axis| voxel[axis] + offset[axis]))
+}
+
+/// Scan of a droplet stored sparsely, so that signed and far apart
+/// coordinates cost nothing extra.
+///
+/// Lava voxels touching at least at a corner form a component. Air is only
+/// flood filled in the shell of cells touching a component, which contains
+/// all of the component's faces. A component inside the cavity of another
+/// one is found by counting the other one's exterior faces along a ray.
+struct Droplet {
+    lava: Vec<Voxel>,
+    /// Component of each lava voxel.
+    components: HashMap<Voxel, usize>,
+    /// Air cells touching lava that are connected to the outside.
+    exterior: HashSet<Voxel>,
+}
+
+impl Droplet {
+    pub fn new(lava: Vec<Voxel>) -> anyhow::Result<Self> {
+        if lava.is_empty() {
+            return Err(InputParseError::new("no input?".into()).into());
+        }
+
+        let occupied: HashSet<Voxel> = lava.iter().copied().collect();
+        let mut components = HashMap::new();
+        let mut members: Vec<Vec<Voxel>> = vec![];
+        for voxel in &lava {
+            if components.contains_key(voxel) {
+                continue;
+            }
+            let id = members.len();
+            let mut member = vec![*voxel];
+            components.insert(*voxel, id);
+            let mut visited = 0;
+            while let Some(&voxel) = member.get(visited) {
+                visited += 1;
+                for other in all_neighbours(&voxel) {
+                    if occupied.contains(&other) && !components.contains_key(&other) {
+                        components.insert(other, id);
+                        member.push(other);
+                    }
+                }
+            }
+            members.push(member);
+        }
+
+        let mut droplet = Self {
+            lava,
+            components,
+            exterior: HashSet::new(),
+        };
+        // Nothing of a component lies further in negative x direction than
+        // the neighbour of its smallest voxel.
+        let seeds: Vec<Voxel> = members
+            .iter()
+            .map(|member| neighbour(member.iter().min().unwrap(), 0, false))
+            .collect();
+        let shells: Vec<HashSet<Voxel>> = members
+            .iter()
+            .zip(&seeds)
+            .map(|(member, seed)| droplet.exterior_shell(member, *seed))
+            .collect();
+
+        // Exterior faces perpendicular to the x axis by row, each with the
+        // x coordinate of the cell on its positive side and its component.
+        let mut crossings: HashMap<[i64; 2], Vec<(i64, usize)>> = HashMap::new();
+        for voxel in &droplet.lava {
+            let id = droplet.components[voxel];
+            for positive in [false, true] {
+                let air = neighbour(voxel, 0, positive);
+                if shells[id].contains(&air) {
+                    crossings
+                        .entry([voxel[1], voxel[2]])
+                        .or_default()
+                        .push((voxel[0].max(air[0]), id));
+                }
+            }
+        }
+        for (seed, shell) in seeds.iter().zip(shells) {
+            let mut inside = HashMap::new();
+            for &(x, id) in crossings.get(&[seed[1], seed[2]]).into_iter().flatten() {
+                if x <= seed[0] {
+                    *inside.entry(id).or_insert(false) ^= true;
+                }
+            }
+            if !inside.values().any(|&inside| inside) {
+                droplet.exterior.extend(shell);
+            }
+        }
+        Ok(droplet)
+    }
+
+    /// Air cells touching a component that are connected to `seed` without
+    /// passing through the component.
+    fn exterior_shell(&self, member: &[Voxel], seed: Voxel) -> HashSet<Voxel> {
+        let candidates: HashSet<Voxel> = member
+            .iter()
+            .flat_map(all_neighbours)
+            .filter(|voxel| !self.components.contains_key(voxel))
+            .collect();
+        let mut shell = HashSet::from([seed]);
+        let mut to_visit = vec![seed];
+        while let Some(voxel) = to_visit.pop() {
+            for other in face_neighbours(&voxel) {
+                if candidates.contains(&other) && shell.insert(other) {
+                    to_visit.push(other);
+                }
+            }
+        }
+        shell
+    }
+
+    fn classify(&self, voxel: &Voxel) -> Classification {
+        if self.components.contains_key(voxel) {
+            Classification::Lava
+        } else if self.exterior.contains(voxel) {
+            Classification::Air
+        } else {
+            Classification::TrappedAir
+        }
+    }
+
+    fn faces(&self, classification: Classification) -> impl Iterator<Item = Face> + '_ {
+        self.lava.iter().flat_map(move |voxel| {
+            (0..3)
+                .flat_map(|axis| [false, true].map(|positive| (axis, positive)))
+                .filter(move |&(axis, positive)| {
+                    self.classify(&neighbour(voxel, axis, positive)) == classification
+                })
+                .map(|(axis, positive)| Face {
+                    voxel: *voxel,
+                    axis,
+                    positive,
+                })
+        })
+    }
+
+    /// Exterior surface as Wavefront OBJ with outward facing quads.
+    pub fn to_obj(&self) -> String {
+        let mut vertices: HashMap<Voxel, usize> = HashMap::new();
+        let mut vertex_lines = String::new();
+        let mut face_lines = String::new();
+        for face in self.faces(Classification::Air) {
+            let (u, v) = ((face.axis + 1) % 3, (face.axis + 2) % 3);
+            let mut corners = [(0, 0), (1, 0), (1, 1), (0, 1)];
+            if !face.positive {
+                corners.reverse();
+            }
+            face_lines.push('f');
+            for (du, dv) in corners {
+                let mut corner = face.voxel;
+                corner[face.axis] += face.positive as i64;
+                corner[u] += du;
+                corner[v] += dv;
+                let next_id = vertices.len() + 1;
+                let id = *vertices.entry(corner).or_insert_with(|| {
+                    writeln!(vertex_lines, "v {} {} {}", corner[0], corner[1], corner[2])
+                        .expect("writing to string failed");
+                    next_id
+                });
+                write!(face_lines, " {}", id).expect("writing to string failed");
+            }
+            face_lines.push('\n');
+        }
+        vertex_lines + &face_lines
+    }
+}
+
 pub struct Solver {
+    droplet: Droplet,
     surface: usize,
     surface_without_interior: usize,
 }
@@ -24,51 +223,20 @@ impl<'a> AocSolver<'a, usize, usize> for Solver {
             .filter(|line| !line.trim().is_empty())
             .map(|line| {
                 let voxel = line
+                    .trim()
                     .split(',')
                     .map(str::parse)
-                    .collect::<Result<Vec<usize>, ParseIntError>>()?;
-                Ok((voxel[0], voxel[1], voxel[2]))
+                    .collect::<Result<Vec<i64>, _>>()?;
+                Ok(voxel.try_into().map_err(|_| {
+                    InputParseError::new(format!("expected three coordinates: {}", line))
+                })?)
             })
-            .collect::<Result<Vec<(usize, usize, usize)>, ParseIntError>>()?;
-        let max_dim = voxels
-            .iter()
-            .map(|voxel| voxel.0.max(voxel.1).max(voxel.2))
-            .max()
-            .ok_or_else(|| InputParseError::new("no input?".into()))?
-            + 1;
-        let mut scan = vec![vec![vec![Classification::TrappedAir; max_dim]; max_dim]; max_dim];
-        for voxel in voxels {
-            scan[voxel.0][voxel.1][voxel.2] = Classification::Lava;
-        }
-
-        let mut to_visit: VecDeque<(usize, usize, usize)> = VecDeque::new();
-        to_visit.push_back((0, 0, 0));
-        while let Some((x, y, z)) = to_visit.pop_front() {
-            if scan[x][y][z] == Classification::TrappedAir {
-                scan[x][y][z] = Classification::Air;
-                if x > 0 {
-                    to_visit.push_back((x - 1, y, z));
-                }
-                if y > 0 {
-                    to_visit.push_back((x, y - 1, z));
-                }
-                if z > 0 {
-                    to_visit.push_back((x, y, z - 1));
-                }
-                if x + 1 < scan.len() {
-                    to_visit.push_back((x + 1, y, z));
-                }
-                if y + 1 < scan[x].len() {
-                    to_visit.push_back((x, y + 1, z));
-                }
-                if z + 1 < scan[x][y].len() {
-                    to_visit.push_back((x, y, z + 1));
-                }
-            }
-        }
-
-        let (surface, surface_interior) = Self::surface(&scan);
+            .collect::<anyhow::Result<Vec<Voxel>>>()?;
+        let droplet = Droplet::new(voxels)?;
+        let surface = droplet.faces(Classification::Air).count();
+        let surface_interior = droplet.faces(Classification::TrappedAir).count();
         Ok(Self {
+            droplet,
             surface: surface + surface_interior,
             surface_without_interior: surface,
         })
@@ -81,82 +249,9 @@ impl<'a> AocSolver<'a, usize, usize> for Solver {
     fn solve_part2(&self) -> anyhow::Result<Option<usize>> {
         Ok(Some(self.surface_without_interior))
     }
-}
 
-impl Solver {
-    fn surface(scan: &Vec<Vec<Vec<Classification>>>) -> (usize, usize) {
-        let mut count = 0;
-        let mut count_interior = 0;
-        for x in 0..scan.len() {
-            for y in 0..scan[x].len() {
-                for z in 0..scan[x][y].len() {
-                    if scan[x][y][z] != Classification::Lava {
-                        continue;
-                    }
-                    if x == 0 {
-                        count += 1;
-                    }
-                    if y == 0 {
-                        count += 1;
-                    }
-                    if z == 0 {
-                        count += 1;
-                    }
-                    if x == scan.len() - 1 {
-                        count += 1;
-                    }
-                    if y == scan[x].len() - 1 {
-                        count += 1;
-                    }
-                    if z == scan[x][y].len() - 1 {
-                        count += 1;
-                    }
-                    if x > 0 {
-                        match scan[x - 1][y][z] {
-                            Classification::Air => count += 1,
-                            Classification::TrappedAir => count_interior += 1,
-                            _ => (),
-                        }
-                    }
-                    if y > 0 {
-                        match scan[x][y - 1][z] {
-                            Classification::Air => count += 1,
-                            Classification::TrappedAir => count_interior += 1,
-                            _ => (),
-                        }
-                    }
-                    if z > 0 {
-                        match scan[x][y][z - 1] {
-                            Classification::Air => count += 1,
-                            Classification::TrappedAir => count_interior += 1,
-                            _ => (),
-                        }
-                    }
-                    if x + 1 < scan.len() {
-                        match scan[x + 1][y][z] {
-                            Classification::Air => count += 1,
-                            Classification::TrappedAir => count_interior += 1,
-                            _ => (),
-                        }
-                    }
-                    if y + 1 < scan[x].len() {
-                        match scan[x][y + 1][z] {
-                            Classification::Air => count += 1,
-                            Classification::TrappedAir => count_interior += 1,
-                            _ => (),
-                        }
-                    }
-                    if z + 1 < scan[x][y].len() {
-                        match scan[x][y][z + 1] {
-                            Classification::Air => count += 1,
-                            Classification::TrappedAir => count_interior += 1,
-                            _ => (),
-                        }
-                    }
-                }
-            }
-        }
-        (count, count_interior)
+    fn visualize(&self) -> anyhow::Result<Option<String>> {
+        Ok(Some(self.droplet.to_obj()))
     }
 }
 
@@ -170,4 +265,135 @@ mod tests {
         let input = include_str!("examples/day18");
         test_example_input::<Solver, _, _>(input, 64, Some(58));
     }
+
+    #[test]
+    fn test_signed_and_far_apart_coordinates() {
+        let input: String = include_str!("examples/day18")
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let coordinates: Vec<i64> = line
+                    .split(',')
+                    .map(|c| c.parse::<i64>().unwrap() - 100)
+                    .collect();
+                format!("{},{},{}\n", coordinates[0], coordinates[1], coordinates[2])
+            })
+            .chain(std::iter::once("1000000000,0,-1000000000\n".into()))
+            .collect();
+        test_example_input::<Solver, _, _>(&input, 70, Some(64));
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        assert!(Solver::new("").is_err());
+        assert!(Solver::new("1,2\n").is_err());
+        assert!(Solver::new("1,2,x\n").is_err());
+    }
+
+    #[test]
+    fn test_obj_export() {
+        let solver = Solver::new("0,0,0\n").unwrap();
+        let obj = solver.droplet.to_obj();
+        assert_eq!(obj.lines().filter(|line| line.starts_with("v ")).count(), 8);
+        assert_eq!(obj.lines().filter(|line| line.starts_with("f ")).count(), 6);
+        assert!(obj.contains("v 1 1 1\n"));
+        assert!(obj.contains("v 0 0 0\n"));
+
+        let solver = Solver::new(include_str!("examples/day18")).unwrap();
+        let obj = solver.droplet.to_obj();
+        assert_eq!(
+            obj.lines().filter(|line| line.starts_with("f ")).count(),
+            58
+        );
+    }
+
+    fn to_input(voxels: &[Voxel]) -> String {
+        voxels
+            .iter()
+            .map(|voxel| format!("{},{},{}\n", voxel[0], voxel[1], voxel[2]))
+            .collect()
+    }
+
+    /// Surface areas with and without interior found by a flood fill of the
+    /// whole bounding box.
+    fn dense_surface(voxels: &[Voxel], size: i64) -> (usize, usize) {
+        let lava: HashSet<Voxel> = voxels.iter().copied().collect();
+        let mut exterior = HashSet::from([[-1, -1, -1]]);
+        let mut to_visit = vec![[-1, -1, -1]];
+        while let Some(voxel) = to_visit.pop() {
+            for other in face_neighbours(&voxel) {
+                if other.iter().all(|&c| (-1..=size).contains(&c))
+                    && !lava.contains(&other)
+                    && exterior.insert(other)
+                {
+                    to_visit.push(other);
+                }
+            }
+        }
+        let faces = || voxels.iter().flat_map(face_neighbours);
+        (
+            faces().filter(|other| !lava.contains(other)).count(),
+            faces().filter(|other| exterior.contains(other)).count(),
+        )
+    }
+
+    #[test]
+    fn test_matches_dense_flood_fill() {
+        let mut state: u64 = 7;
+        let mut random = |bound: u64| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) % bound
+        };
+        for _ in 0..60 {
+            let size = 3 + random(6) as i64;
+            let density = 20 + random(60);
+            let voxels: Vec<Voxel> = (0..size)
+                .flat_map(|x| (0..size).flat_map(move |y| (0..size).map(move |z| [x, y, z])))
+                .filter(|_| random(100) < density)
+                .collect();
+            if voxels.is_empty() {
+                continue;
+            }
+            let solver = Solver::new(&to_input(&voxels)).unwrap();
+            let (surface, exterior_surface) = dense_surface(&voxels, size);
+            assert_eq!(solver.solve_part1().unwrap(), surface);
+            assert_eq!(solver.solve_part2().unwrap(), Some(exterior_surface));
+        }
+    }
+
+    #[test]
+    fn test_nested_components() {
+        let hollow_cube = |min: i64, max: i64| {
+            (min..=max)
+                .flat_map(move |x| {
+                    (min..=max).flat_map(move |y| (min..=max).map(move |z| [x, y, z]))
+                })
+                .filter(move |voxel| voxel.iter().any(|&c| c == min || c == max))
+        };
+        let voxels: Vec<Voxel> = hollow_cube(0, 8)
+            .chain(hollow_cube(2, 6))
+            .chain(std::iter::once([4, 4, 4]))
+            .collect();
+        let solver = Solver::new(&to_input(&voxels)).unwrap();
+        assert_eq!(
+            (
+                solver.solve_part1().unwrap(),
+                solver.solve_part2().unwrap().unwrap()
+            ),
+            dense_surface(&voxels, 9)
+        );
+        assert_eq!(solver.solve_part2().unwrap(), Some(6 * 81));
+    }
+
+    #[test]
+    fn test_scattered_cubes() {
+        let voxels: Vec<Voxel> = (0..300)
+            .map(|i| [i * 1_000_003, -i * 999_983, (i % 7) * 1_000_000_007])
+            .collect();
+        let solver = Solver::new(&to_input(&voxels)).unwrap();
+        assert_eq!(solver.solve_part1().unwrap(), 1800);
+        assert_eq!(solver.solve_part2().unwrap(), Some(1800));
+    }
 }
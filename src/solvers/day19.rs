@@ -1,6 +1,6 @@
-use std::collections::HashMap;
+use std::fmt::Display;
 
-use super::{base::AocSolver, day01::TopK, error::InputParseError};
+use super::{base::AocSolver, error::InputParseError};
 use lazy_static::lazy_static;
 use regex::Regex;
 
@@ -65,10 +65,6 @@ struct Resources {
 }
 
 impl Resources {
-    fn cover(&self, cost: &Cost) -> bool {
-        self.ore >= cost.ore && self.clay >= cost.clay && self.obsidian >= cost.obsidian
-    }
-
     fn pay(&self, cost: &Cost) -> Self {
         Self {
             ore: self.ore - cost.ore,
@@ -77,12 +73,35 @@ impl Resources {
         }
     }
 
-    fn produce(&self, producers: &RobotCounts) -> Self {
+    fn produce(&self, producers: &RobotCounts, minutes: usize) -> Self {
         Self {
-            ore: self.ore + producers.ore,
-            clay: self.clay + producers.clay,
-            obsidian: self.obsidian + producers.obsidian,
+            ore: self.ore + producers.ore * minutes,
+            clay: self.clay + producers.clay * minutes,
+            obsidian: self.obsidian + producers.obsidian * minutes,
+        }
+    }
+
+    /// Number of minutes until the resources cover the cost given the
+    /// current production, or `None` if the cost can never be covered.
+    fn minutes_until_covered(&self, cost: &Cost, producers: &RobotCounts) -> Option<usize> {
+        fn minutes_for(available: usize, required: usize, production: usize) -> Option<usize> {
+            if available >= required {
+                Some(0)
+            } else if production == 0 {
+                None
+            } else {
+                Some((required - available).div_ceil(production))
+            }
         }
+        Some(
+            minutes_for(self.ore, cost.ore, producers.ore)?
+                .max(minutes_for(self.clay, cost.clay, producers.clay)?)
+                .max(minutes_for(
+                    self.obsidian,
+                    cost.obsidian,
+                    producers.obsidian,
+                )?),
+        )
     }
 }
 
@@ -93,21 +112,100 @@ struct RobotCounts {
     obsidian: usize,
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Robot {
+    Ore,
+    Clay,
+    Obsidian,
+    Geode,
+}
+
+impl Display for Robot {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Robot::Ore => "ore-collecting robot",
+            Robot::Clay => "clay-collecting robot",
+            Robot::Obsidian => "obsidian-collecting robot",
+            Robot::Geode => "geode-cracking robot",
+        })
+    }
+}
+
+/// Robot build order listing the minute in which each robot gets built.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BuildPlan {
+    pub minutes: usize,
+    pub builds: Vec<(usize, Robot)>,
+}
+
+impl Display for BuildPlan {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut builds = self.builds.iter().peekable();
+        for minute in 1..=self.minutes {
+            match builds.next_if(|(build_minute, _)| *build_minute == minute) {
+                Some((_, robot)) => writeln!(f, "Minute {:>2}: build {}", minute, robot)?,
+                None => writeln!(f, "Minute {:>2}: wait", minute)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Exact branch-and-bound search for the maximum number of geodes.
+///
+/// Instead of deciding what to do in each minute, the search decides which
+/// robot to build next and skips over the minutes needed to gather the
+/// resources for it.
 struct GeodeMaximizer<'a> {
     blueprint: &'a Blueprint,
-    cache: HashMap<(usize, Resources, RobotCounts), usize>,
+    max_useful: RobotCounts,
+    minutes: usize,
+    best: usize,
+    builds: Vec<(usize, Robot)>,
+    best_plan: BuildPlan,
 }
 
 impl<'a> GeodeMaximizer<'a> {
     pub fn new(blueprint: &'a Blueprint) -> Self {
+        let costs = [
+            &blueprint.cost_robot_ore,
+            &blueprint.cost_robot_clay,
+            &blueprint.cost_robot_obsidian,
+            &blueprint.cost_robot_geode,
+        ];
         Self {
             blueprint,
-            cache: HashMap::new(),
+            max_useful: RobotCounts {
+                ore: costs.iter().map(|cost| cost.ore).max().unwrap_or_default(),
+                clay: costs.iter().map(|cost| cost.clay).max().unwrap_or_default(),
+                obsidian: costs
+                    .iter()
+                    .map(|cost| cost.obsidian)
+                    .max()
+                    .unwrap_or_default(),
+            },
+            minutes: 0,
+            best: 0,
+            builds: vec![],
+            best_plan: BuildPlan::default(),
         }
     }
 
     pub fn maximize(&mut self, minutes: usize) -> usize {
-        self._maximize(
+        self.plan(minutes);
+        self.best
+    }
+
+    /// Determines an optimal build order for the given number of minutes.
+    pub fn plan(&mut self, minutes: usize) -> &BuildPlan {
+        self.minutes = minutes;
+        self.best = 0;
+        self.builds.clear();
+        self.best_plan = BuildPlan {
+            minutes,
+            builds: vec![],
+        };
+        self.search(
             minutes,
             Resources::default(),
             RobotCounts {
@@ -115,95 +213,77 @@ impl<'a> GeodeMaximizer<'a> {
                 clay: 0,
                 obsidian: 0,
             },
-        )
+            0,
+        );
+        &self.best_plan
+    }
+
+    fn cost(&self, robot: Robot) -> &'a Cost {
+        match robot {
+            Robot::Ore => &self.blueprint.cost_robot_ore,
+            Robot::Clay => &self.blueprint.cost_robot_clay,
+            Robot::Obsidian => &self.blueprint.cost_robot_obsidian,
+            Robot::Geode => &self.blueprint.cost_robot_geode,
+        }
+    }
+
+    fn is_useful(&self, robot: Robot, count_robots: &RobotCounts) -> bool {
+        match robot {
+            Robot::Ore => count_robots.ore < self.max_useful.ore,
+            Robot::Clay => count_robots.clay < self.max_useful.clay,
+            Robot::Obsidian => count_robots.obsidian < self.max_useful.obsidian,
+            Robot::Geode => true,
+        }
     }
 
-    fn _maximize(
+    /// Optimistic bound assuming a geode robot could be built every
+    /// remaining minute.
+    fn upper_bound(remaining_minutes: usize, geodes: usize) -> usize {
+        geodes + remaining_minutes * remaining_minutes.saturating_sub(1) / 2
+    }
+
+    /// `geodes` is the total number of geodes that the geode robots built so
+    /// far will have opened by the end.
+    fn search(
         &mut self,
         remaining_minutes: usize,
         resources: Resources,
         count_robots: RobotCounts,
-    ) -> usize {
-        if remaining_minutes == 0 {
-            return 0;
+        geodes: usize,
+    ) {
+        if geodes > self.best {
+            self.best = geodes;
+            self.best_plan.builds.clone_from(&self.builds);
         }
-        let cache_key = (remaining_minutes, resources, count_robots);
-        if let Some(result) = self.cache.get(&cache_key) {
-            return *result;
+        if Self::upper_bound(remaining_minutes, geodes) <= self.best {
+            return;
         }
 
-        let mut best = TopK::<usize, 1>::new();
-
-        if resources.cover(&self.blueprint.cost_robot_geode) {
-            best.push(
-                self._maximize(
-                    remaining_minutes - 1,
-                    resources
-                        .pay(&self.blueprint.cost_robot_geode)
-                        .produce(&count_robots),
-                    RobotCounts {
-                        ore: count_robots.ore,
-                        clay: count_robots.clay,
-                        obsidian: count_robots.obsidian,
-                    },
-                ) + remaining_minutes
-                    - 1,
-            )
-        } else if resources.cover(&self.blueprint.cost_robot_obsidian) {
-            best.push(
-                self._maximize(
-                    remaining_minutes - 1,
-                    resources
-                        .pay(&self.blueprint.cost_robot_obsidian)
-                        .produce(&count_robots),
-                    RobotCounts {
-                        ore: count_robots.ore,
-                        clay: count_robots.clay,
-                        obsidian: count_robots.obsidian + 1,
-                    },
-                ),
-            )
-        } else {
-            if resources.cover(&self.blueprint.cost_robot_ore) {
-                best.push(
-                    self._maximize(
-                        remaining_minutes - 1,
-                        resources
-                            .pay(&self.blueprint.cost_robot_ore)
-                            .produce(&count_robots),
-                        RobotCounts {
-                            ore: count_robots.ore + 1,
-                            clay: count_robots.clay,
-                            obsidian: count_robots.obsidian,
-                        },
-                    ),
-                )
+        for robot in [Robot::Geode, Robot::Obsidian, Robot::Clay, Robot::Ore] {
+            if !self.is_useful(robot, &count_robots) {
+                continue;
             }
-            if resources.cover(&self.blueprint.cost_robot_clay) {
-                best.push(
-                    self._maximize(
-                        remaining_minutes - 1,
-                        resources
-                            .pay(&self.blueprint.cost_robot_clay)
-                            .produce(&count_robots),
-                        RobotCounts {
-                            ore: count_robots.ore,
-                            clay: count_robots.clay + 1,
-                            obsidian: count_robots.obsidian,
-                        },
-                    ),
-                )
+            let cost = self.cost(robot);
+            let Some(wait) = resources.minutes_until_covered(cost, &count_robots) else {
+                continue;
+            };
+            if wait + 1 >= remaining_minutes {
+                continue;
             }
+            let remaining_minutes = remaining_minutes - wait - 1;
+            let resources = resources.produce(&count_robots, wait + 1).pay(cost);
+            let mut count_robots = count_robots;
+            let mut geodes = geodes;
+            match robot {
+                Robot::Ore => count_robots.ore += 1,
+                Robot::Clay => count_robots.clay += 1,
+                Robot::Obsidian => count_robots.obsidian += 1,
+                Robot::Geode => geodes += remaining_minutes,
+            }
+            self.builds.push((self.minutes - remaining_minutes, robot));
+            self.search(remaining_minutes, resources, count_robots, geodes);
+            self.builds.pop();
         }
-        best.push(self._maximize(
-            remaining_minutes - 1,
-            resources.produce(&count_robots),
-            count_robots,
-        ));
-
-        let result = best.peek().copied().unwrap_or_default();
-        self.cache.insert(cache_key, result);
-        result
     }
 }
 
@@ -225,39 +305,137 @@ impl<'a> AocSolver<'a, usize, usize> for Solver {
     }
 
     fn solve_part1(&self) -> anyhow::Result<usize> {
-        Ok(self
-            .blueprints
-            .iter()
-            .map(|blueprint| {
-                let mut maximizer = GeodeMaximizer::new(blueprint);
-                blueprint.id * maximizer.maximize(24)
-            })
-            .sum())
+        Ok(parallel_map(&self.blueprints, |blueprint| {
+            blueprint.id * GeodeMaximizer::new(blueprint).maximize(24)
+        })
+        .into_iter()
+        .sum())
     }
 
     fn solve_part2(&self) -> anyhow::Result<Option<usize>> {
+        let first_blueprints = &self.blueprints[..self.blueprints.len().min(3)];
         Ok(Some(
-            self.blueprints
-                .iter()
-                .take(3)
-                .map(|blueprint| {
-                    let mut maximizer = GeodeMaximizer::new(blueprint);
-                    maximizer.maximize(32)
-                })
-                .product(),
+            parallel_map(first_blueprints, |blueprint| {
+                GeodeMaximizer::new(blueprint).maximize(32)
+            })
+            .into_iter()
+            .product(),
         ))
     }
+
+    fn visualize(&self) -> anyhow::Result<Option<String>> {
+        let mut output = String::from("    id | geodes (24) | geodes (32) | quality level\n");
+        output.push_str("-------+-------------+-------------+--------------\n");
+        for report in self.report() {
+            output.push_str(&format!(
+                "{:>6} | {:>11} | {:>11} | {:>13}\n",
+                report.id, report.max_geodes_24, report.max_geodes_32, report.quality_level
+            ));
+        }
+        for (blueprint, plan) in self.blueprints.iter().zip(self.build_plans(24)) {
+            output.push_str(&format!("\nBlueprint {}:\n{}", blueprint.id, plan));
+        }
+        Ok(Some(output))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlueprintReport {
+    pub id: usize,
+    pub max_geodes_24: usize,
+    pub max_geodes_32: usize,
+    pub quality_level: usize,
+}
+
+impl Solver {
+    pub fn report(&self) -> Vec<BlueprintReport> {
+        parallel_map(&self.blueprints, |blueprint| {
+            let mut maximizer = GeodeMaximizer::new(blueprint);
+            let max_geodes_24 = maximizer.maximize(24);
+            let max_geodes_32 = maximizer.maximize(32);
+            BlueprintReport {
+                id: blueprint.id,
+                max_geodes_24,
+                max_geodes_32,
+                quality_level: blueprint.id * max_geodes_24,
+            }
+        })
+    }
+
+    pub fn build_plans(&self, minutes: usize) -> Vec<BuildPlan> {
+        parallel_map(&self.blueprints, |blueprint| {
+            GeodeMaximizer::new(blueprint).plan(minutes).clone()
+        })
+    }
+}
+
+/// Maps the items with `f` distributed over all available cores, keeping
+/// the order of the items.
+fn parallel_map<T: Sync, R: Send>(items: &[T], f: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    let num_threads = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1);
+    let chunk_size = items.len().div_ceil(num_threads).max(1);
+    std::thread::scope(|scope| {
+        let f = &f;
+        let handles: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(f).collect::<Vec<R>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("worker thread panicked"))
+            .collect()
+    })
 }
 
 #[cfg(test)]
 mod tests {
-    // too slow to run by default
-    // use super::*;
-
-    // #[test]
-    // fn test_example() {
-    //     let input = include_str!("examples/day19");
-    //     let solver = Solver::new(input).unwrap();
-    //     assert_eq!(solver.solve_part1().unwrap(), 33);
-    // }
+    use super::*;
+    use crate::solvers::test::test_example_input;
+
+    #[test]
+    fn test_example() {
+        let input = include_str!("examples/day19");
+        test_example_input::<Solver, _, _>(input, 33, Some(56 * 62));
+    }
+
+    #[test]
+    fn test_report() {
+        let input = include_str!("examples/day19");
+        let solver = Solver::new(input).unwrap();
+        assert_eq!(
+            solver.report(),
+            vec![
+                BlueprintReport {
+                    id: 1,
+                    max_geodes_24: 9,
+                    max_geodes_32: 56,
+                    quality_level: 9,
+                },
+                BlueprintReport {
+                    id: 2,
+                    max_geodes_24: 12,
+                    max_geodes_32: 62,
+                    quality_level: 24,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_build_plan() {
+        let input = include_str!("examples/day19");
+        let solver = Solver::new(input).unwrap();
+        let plan = &solver.build_plans(24)[0];
+        assert_eq!(plan.minutes, 24);
+        let geodes: usize = plan
+            .builds
+            .iter()
+            .filter(|(_, robot)| *robot == Robot::Geode)
+            .map(|(minute, _)| 24 - minute)
+            .sum();
+        assert_eq!(geodes, 9);
+        assert!(plan.to_string().starts_with("Minute  1: wait\n"));
+    }
 }
@@ -2,44 +2,81 @@ use std::num::ParseIntError;
 
 use super::base::AocSolver;
 
-struct DoublyLinkedList<T> {
+/// Circular list split into blocks of roughly `sqrt(n)` elements, so that
+/// moving an element costs `O(sqrt(n))` instead of `O(n)`.
+///
+/// Elements are referred to by their index in the original order.
+struct BlockList<T> {
     values: Vec<T>,
-    next: Vec<usize>,
-    prev: Vec<usize>,
+    blocks: Vec<Vec<usize>>,
+    block_of: Vec<usize>,
+    block_size: usize,
 }
 
-impl<T> DoublyLinkedList<T> {
-    #[allow(clippy::needless_range_loop)]
+impl<T> BlockList<T> {
     fn new(values: Vec<T>) -> Self {
-        let mut next: Vec<usize> = (1..=values.len()).collect();
-        *next.last_mut().unwrap() = 0;
-        let mut prev: Vec<usize> = vec![0; values.len()];
-        prev[0] = values.len() - 1;
-        for i in 1..prev.len() {
-            prev[i] = i - 1;
+        let block_size = ((values.len() as f64).sqrt() as usize).max(1);
+        let mut list = Self {
+            blocks: vec![(0..values.len()).collect()],
+            block_of: vec![0; values.len()],
+            values,
+            block_size,
+        };
+        list.rebuild();
+        list
+    }
+
+    fn rebuild(&mut self) {
+        let order: Vec<usize> = self.blocks.iter().flatten().copied().collect();
+        self.blocks = order
+            .chunks(self.block_size)
+            .map(|chunk| chunk.to_vec())
+            .collect();
+        for (block_index, block) in self.blocks.iter().enumerate() {
+            for &index in block {
+                self.block_of[index] = block_index;
+            }
+        }
+    }
+
+    fn position(&self, index: usize) -> usize {
+        let block_index = self.block_of[index];
+        let preceding: usize = self.blocks[..block_index].iter().map(Vec::len).sum();
+        preceding
+            + self.blocks[block_index]
+                .iter()
+                .position(|&i| i == index)
+                .expect("block_of out of sync")
+    }
+
+    /// Returns the block and offset within that block for a position. A
+    /// position equal to the length maps to the end of the last block.
+    fn locate(&self, mut position: usize) -> (usize, usize) {
+        for (block_index, block) in self.blocks.iter().enumerate() {
+            if position < block.len() {
+                return (block_index, position);
+            }
+            position -= block.len();
         }
-        Self { values, next, prev }
+        let last = self.blocks.len() - 1;
+        (last, self.blocks[last].len())
     }
 
     fn move_by(&mut self, index: usize, dist: isize) {
-        let mut dist = dist % (self.values.len() as isize - 1);
-        self.next[self.prev[index]] = self.next[index];
-        self.prev[self.next[index]] = self.prev[index];
-
-        let mut insertion_point = self.next[index];
-        while dist > 0 {
-            insertion_point = self.next[insertion_point];
-            dist -= 1;
+        if self.len() <= 1 {
+            return;
         }
-        while dist < 0 {
-            insertion_point = self.prev[insertion_point];
-            dist += 1;
+        let position = self.position(index);
+        let (block_index, offset) = self.locate(position);
+        self.blocks[block_index].remove(offset);
+
+        let new_position = (position as isize + dist).rem_euclid(self.len() as isize - 1);
+        let (block_index, offset) = self.locate(new_position as usize);
+        self.blocks[block_index].insert(offset, index);
+        self.block_of[index] = block_index;
+        if self.blocks[block_index].len() > 2 * self.block_size {
+            self.rebuild();
         }
-
-        self.next[index] = insertion_point;
-        self.prev[index] = self.prev[insertion_point];
-        self.next[self.prev[insertion_point]] = index;
-        self.prev[insertion_point] = index;
     }
 
     fn len(&self) -> usize {
@@ -50,12 +87,9 @@ impl<T> DoublyLinkedList<T> {
         &self.values
     }
 
-    fn advance_by(&self, mut index: usize, mut dist: usize) -> usize {
-        while dist > 0 {
-            index = self.next[index];
-            dist -= 1;
-        }
-        index
+    fn advance_by(&self, index: usize, dist: usize) -> usize {
+        let (block_index, offset) = self.locate((self.position(index) + dist) % self.len());
+        self.blocks[block_index][offset]
     }
 }
 
@@ -77,7 +111,7 @@ impl<'a> AocSolver<'a, isize, isize> for Solver {
     }
 
     fn solve_part1(&self) -> anyhow::Result<isize> {
-        let mut list = DoublyLinkedList::new(self.input.clone());
+        let mut list = BlockList::new(self.input.clone());
         for i in 0..list.len() {
             list.move_by(i, list.values()[i]);
         }
@@ -85,7 +119,7 @@ impl<'a> AocSolver<'a, isize, isize> for Solver {
     }
 
     fn solve_part2(&self) -> anyhow::Result<Option<isize>> {
-        let mut list = DoublyLinkedList::new(self.input.iter().map(|v| v * 811589153).collect());
+        let mut list = BlockList::new(self.input.iter().map(|v| v * 811589153).collect());
         for _ in 0..10 {
             for i in 0..list.len() {
                 list.move_by(i, list.values()[i]);
@@ -96,7 +130,7 @@ impl<'a> AocSolver<'a, isize, isize> for Solver {
 }
 
 impl Solver {
-    fn get_solution(list: &DoublyLinkedList<isize>) -> anyhow::Result<isize> {
+    fn get_solution(list: &BlockList<isize>) -> anyhow::Result<isize> {
         let zero_index = list
             .values()
             .iter()
@@ -121,4 +155,34 @@ mod tests {
         let input = include_str!("examples/day20");
         test_example_input::<Solver, _, _>(input, 3, Some(1623178306));
     }
+
+    #[test]
+    fn test_matches_naive_mixing() {
+        let mut seed: u64 = 42;
+        let values: Vec<isize> = (0..997)
+            .map(|_| {
+                seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+                (seed >> 33) as isize % 20000 - 10000
+            })
+            .collect();
+
+        let mut list = BlockList::new(values.clone());
+        let mut naive: Vec<usize> = (0..values.len()).collect();
+        for _ in 0..2 {
+            for i in 0..values.len() {
+                list.move_by(i, values[i]);
+
+                let position = naive.iter().position(|&j| j == i).unwrap();
+                naive.remove(position);
+                let new_position =
+                    (position as isize + values[i]).rem_euclid(values.len() as isize - 1);
+                naive.insert(new_position as usize, i);
+            }
+        }
+
+        let start = naive[0];
+        for (dist, &expected) in naive.iter().enumerate() {
+            assert_eq!(list.advance_by(start, dist), expected);
+        }
+    }
 }
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt::Display};
 
 use super::{base::AocSolver, error::InputParseError};
 
@@ -33,6 +33,281 @@ impl<'a> Statement<'a> {
     }
 }
 
+impl<'a> Display for Statement<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Statement::Const(value) => write!(f, "{}", value),
+            Statement::Add(lhs, rhs) => write!(f, "{} {} {}", lhs, Operator::Add, rhs),
+            Statement::Sub(lhs, rhs) => write!(f, "{} {} {}", lhs, Operator::Sub, rhs),
+            Statement::Mul(lhs, rhs) => write!(f, "{} {} {}", lhs, Operator::Mul, rhs),
+            Statement::Div(lhs, rhs) => write!(f, "{} {} {}", lhs, Operator::Div, rhs),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Operator {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl Operator {
+    fn precedence(&self) -> u8 {
+        match self {
+            Operator::Add | Operator::Sub => 0,
+            Operator::Mul | Operator::Div => 1,
+        }
+    }
+
+    fn is_commutative(&self) -> bool {
+        matches!(self, Operator::Add | Operator::Mul)
+    }
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Operator::Add => "+",
+            Operator::Sub => "-",
+            Operator::Mul => "*",
+            Operator::Div => "/",
+        })
+    }
+}
+
+/// Exact fraction with the sign kept in the numerator.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Rational {
+    numerator: i128,
+    denominator: i128,
+}
+
+impl Rational {
+    pub const ZERO: Self = Self {
+        numerator: 0,
+        denominator: 1,
+    };
+    pub const ONE: Self = Self {
+        numerator: 1,
+        denominator: 1,
+    };
+
+    pub fn new(numerator: i128, denominator: i128) -> anyhow::Result<Self> {
+        if denominator == 0 {
+            return Err(anyhow::anyhow!("division by zero"));
+        }
+        let divisor = gcd(numerator, denominator);
+        let sign = denominator.signum();
+        Ok(Self {
+            numerator: sign * (numerator / divisor),
+            denominator: sign * (denominator / divisor),
+        })
+    }
+
+    pub fn integer(&self) -> Option<i128> {
+        if self.denominator == 1 {
+            Some(self.numerator)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.numerator == 0
+    }
+
+    fn checked_add(self, other: Self) -> anyhow::Result<Self> {
+        let numerator = self
+            .numerator
+            .checked_mul(other.denominator)
+            .zip(other.numerator.checked_mul(self.denominator))
+            .and_then(|(lhs, rhs)| lhs.checked_add(rhs))
+            .ok_or_else(|| anyhow::anyhow!("overflow"))?;
+        let denominator = self
+            .denominator
+            .checked_mul(other.denominator)
+            .ok_or_else(|| anyhow::anyhow!("overflow"))?;
+        Self::new(numerator, denominator)
+    }
+
+    fn checked_neg(self) -> anyhow::Result<Self> {
+        Ok(Self {
+            numerator: self
+                .numerator
+                .checked_neg()
+                .ok_or_else(|| anyhow::anyhow!("overflow"))?,
+            denominator: self.denominator,
+        })
+    }
+
+    fn checked_sub(self, other: Self) -> anyhow::Result<Self> {
+        self.checked_add(other.checked_neg()?)
+    }
+
+    fn checked_mul(self, other: Self) -> anyhow::Result<Self> {
+        let numerator = self
+            .numerator
+            .checked_mul(other.numerator)
+            .ok_or_else(|| anyhow::anyhow!("overflow"))?;
+        let denominator = self
+            .denominator
+            .checked_mul(other.denominator)
+            .ok_or_else(|| anyhow::anyhow!("overflow"))?;
+        Self::new(numerator, denominator)
+    }
+
+    fn checked_div(self, other: Self) -> anyhow::Result<Self> {
+        self.checked_mul(Self::new(other.denominator, other.numerator)?)
+    }
+}
+
+impl From<i64> for Rational {
+    fn from(value: i64) -> Self {
+        Self {
+            numerator: value.into(),
+            denominator: 1,
+        }
+    }
+}
+
+impl Display for Rational {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.denominator == 1 {
+            write!(f, "{}", self.numerator)
+        } else {
+            write!(f, "{}/{}", self.numerator, self.denominator)
+        }
+    }
+}
+
+fn gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a.max(1)
+}
+
+/// Expression of the form `coefficient * x + constant` in a single unknown.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Linear {
+    coefficient: Rational,
+    constant: Rational,
+}
+
+impl Linear {
+    fn constant(value: Rational) -> Self {
+        Self {
+            coefficient: Rational::ZERO,
+            constant: value,
+        }
+    }
+
+    fn add(self, other: Self) -> anyhow::Result<Self> {
+        Ok(Self {
+            coefficient: self.coefficient.checked_add(other.coefficient)?,
+            constant: self.constant.checked_add(other.constant)?,
+        })
+    }
+
+    fn sub(self, other: Self) -> anyhow::Result<Self> {
+        Ok(Self {
+            coefficient: self.coefficient.checked_sub(other.coefficient)?,
+            constant: self.constant.checked_sub(other.constant)?,
+        })
+    }
+
+    fn mul(self, other: Self) -> anyhow::Result<Self> {
+        let (linear, factor) = if other.coefficient.is_zero() {
+            (self, other.constant)
+        } else if self.coefficient.is_zero() {
+            (other, self.constant)
+        } else {
+            return Err(anyhow::anyhow!("equation is not linear"));
+        };
+        Ok(Self {
+            coefficient: linear.coefficient.checked_mul(factor)?,
+            constant: linear.constant.checked_mul(factor)?,
+        })
+    }
+
+    fn div(self, other: Self) -> anyhow::Result<Self> {
+        if !other.coefficient.is_zero() {
+            return Err(anyhow::anyhow!("equation is not linear"));
+        }
+        Ok(Self {
+            coefficient: self.coefficient.checked_div(other.constant)?,
+            constant: self.constant.checked_div(other.constant)?,
+        })
+    }
+}
+
+/// Expression tree of monkey jobs where some monkeys may be kept as unknowns.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Expression<'a> {
+    Constant(Rational),
+    Unknown(&'a str),
+    Binary(Operator, Box<Expression<'a>>, Box<Expression<'a>>),
+}
+
+impl<'a> Expression<'a> {
+    /// Folds all subtrees without unknowns into constants.
+    pub fn simplify(self) -> anyhow::Result<Self> {
+        match self {
+            Expression::Binary(operator, lhs, rhs) => match (lhs.simplify()?, rhs.simplify()?) {
+                (Expression::Constant(lhs), Expression::Constant(rhs)) => {
+                    Ok(Expression::Constant(match operator {
+                        Operator::Add => lhs.checked_add(rhs)?,
+                        Operator::Sub => lhs.checked_sub(rhs)?,
+                        Operator::Mul => lhs.checked_mul(rhs)?,
+                        Operator::Div => lhs.checked_div(rhs)?,
+                    }))
+                }
+                (lhs, rhs) => Ok(Expression::Binary(operator, Box::new(lhs), Box::new(rhs))),
+            },
+            expression => Ok(expression),
+        }
+    }
+
+    fn fmt_operand(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+        parent: Operator,
+        is_rhs: bool,
+    ) -> std::fmt::Result {
+        let needs_parentheses = match self {
+            Expression::Binary(operator, _, _) => {
+                operator.precedence() < parent.precedence()
+                    || (is_rhs
+                        && operator.precedence() == parent.precedence()
+                        && !parent.is_commutative())
+            }
+            _ => false,
+        };
+        if needs_parentheses {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+impl<'a> Display for Expression<'a> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expression::Constant(value) => write!(f, "{}", value),
+            Expression::Unknown(name) => f.write_str(name),
+            Expression::Binary(operator, lhs, rhs) => {
+                lhs.fmt_operand(f, *operator, false)?;
+                write!(f, " {} ", operator)?;
+                rhs.fmt_operand(f, *operator, true)
+            }
+        }
+    }
+}
+
 pub struct Solver<'a> {
     monkeys: HashMap<&'a str, Statement<'a>>,
 }
@@ -78,97 +353,212 @@ impl<'a> AocSolver<'a, i64, i64> for Solver<'a> {
             }
         }
 
+        Self::validate(&monkeys)?;
         Ok(Self { monkeys })
     }
 
     fn solve_part1(&self) -> anyhow::Result<i64> {
-        Ok(Self::eval(&self.monkeys, "root"))
+        self.eval("root")
     }
 
     fn solve_part2(&self) -> anyhow::Result<Option<i64>> {
-        let (with_human, others) = if Self::contains(
-            &self.monkeys,
-            self.monkeys
-                .get("root")
-                .unwrap()
-                .lhs()
-                .ok_or_else(|| InputParseError::new("invalid root".into()))?,
-            "humn",
-        ) {
-            (
-                self.monkeys.get("root").unwrap().lhs().unwrap(),
-                self.monkeys.get("root").unwrap().rhs().unwrap(),
-            )
-        } else {
-            (
-                self.monkeys.get("root").unwrap().rhs().unwrap(),
-                self.monkeys.get("root").unwrap().lhs().unwrap(),
-            )
-        };
-        let target_value = Self::eval(&self.monkeys, others);
+        let solution = self.solve_for("humn")?;
+        let solution = solution
+            .integer()
+            .and_then(|value| i64::try_from(value).ok())
+            .ok_or_else(|| anyhow::anyhow!("solution {} is not an integer", solution))?;
+        Ok(Some(solution))
+    }
 
-        Ok(Some(Self::solve(&self.monkeys, with_human, target_value)))
+    fn visualize(&self) -> anyhow::Result<Option<String>> {
+        let root = self.statement("root")?;
+        let (lhs, rhs) = root
+            .lhs()
+            .zip(root.rhs())
+            .ok_or_else(|| anyhow::anyhow!("root must compare two monkeys"))?;
+        Ok(Some(format!(
+            "{} = {}\n\n{}",
+            self.expression(lhs, &["humn"])?.simplify()?,
+            self.expression(rhs, &["humn"])?.simplify()?,
+            self.to_dot()
+        )))
     }
 }
 
 impl<'a> Solver<'a> {
-    fn eval(monkeys: &HashMap<&'a str, Statement<'a>>, node: &'a str) -> i64 {
-        match *monkeys.get(node).unwrap() {
-            Statement::Const(value) => value,
-            Statement::Add(lhs, rhs) => Self::eval(monkeys, lhs) + Self::eval(monkeys, rhs),
-            Statement::Sub(lhs, rhs) => Self::eval(monkeys, lhs) - Self::eval(monkeys, rhs),
-            Statement::Mul(lhs, rhs) => Self::eval(monkeys, lhs) * Self::eval(monkeys, rhs),
-            Statement::Div(lhs, rhs) => Self::eval(monkeys, lhs) / Self::eval(monkeys, rhs),
+    /// Ensures that all referenced monkeys exist and that no monkey depends
+    /// on its own result.
+    fn validate(monkeys: &HashMap<&'a str, Statement<'a>>) -> Result<(), InputParseError> {
+        #[derive(Clone, Copy, PartialEq, Eq)]
+        enum Mark {
+            InProgress,
+            Done,
         }
-    }
 
-    fn contains(monkeys: &HashMap<&'a str, Statement<'a>>, node: &'a str, needle: &'a str) -> bool {
-        if node == needle {
-            return true;
-        }
-        match monkeys.get(node).unwrap() {
-            Statement::Const(_) => false,
-            statement => {
-                Self::contains(monkeys, statement.lhs().unwrap(), needle)
-                    || Self::contains(monkeys, statement.rhs().unwrap(), needle)
+        fn visit<'a>(
+            monkeys: &HashMap<&'a str, Statement<'a>>,
+            marks: &mut HashMap<&'a str, Mark>,
+            node: &'a str,
+        ) -> Result<(), InputParseError> {
+            match marks.get(node) {
+                Some(Mark::Done) => return Ok(()),
+                Some(Mark::InProgress) => {
+                    return Err(InputParseError::new(format!(
+                        "cyclic dependency involving {}",
+                        node
+                    )))
+                }
+                None => {}
             }
+            marks.insert(node, Mark::InProgress);
+            let statement = monkeys
+                .get(node)
+                .ok_or_else(|| InputParseError::new(format!("undefined monkey {}", node)))?;
+            for operand in statement.lhs().into_iter().chain(statement.rhs()) {
+                visit(monkeys, marks, operand)?;
+            }
+            marks.insert(node, Mark::Done);
+            Ok(())
         }
+
+        let mut marks = HashMap::new();
+        for &node in monkeys.keys() {
+            visit(monkeys, &mut marks, node)?;
+        }
+        Ok(())
     }
 
-    fn solve(monkeys: &HashMap<&'a str, Statement<'a>>, node: &'a str, target_value: i64) -> i64 {
-        if node == "humn" {
-            return target_value;
+    /// Builds the expression computed by `node`, keeping the monkeys in
+    /// `unknowns` symbolic.
+    pub fn expression(&self, node: &'a str, unknowns: &[&str]) -> anyhow::Result<Expression<'a>> {
+        if unknowns.contains(&node) {
+            return Ok(Expression::Unknown(node));
+        }
+        match *self.statement(node)? {
+            Statement::Const(value) => Ok(Expression::Constant(value.into())),
+            Statement::Add(lhs, rhs) => self.binary_expression(Operator::Add, lhs, rhs, unknowns),
+            Statement::Sub(lhs, rhs) => self.binary_expression(Operator::Sub, lhs, rhs, unknowns),
+            Statement::Mul(lhs, rhs) => self.binary_expression(Operator::Mul, lhs, rhs, unknowns),
+            Statement::Div(lhs, rhs) => self.binary_expression(Operator::Div, lhs, rhs, unknowns),
         }
+    }
 
-        let node = monkeys.get(node).unwrap();
+    fn binary_expression(
+        &self,
+        operator: Operator,
+        lhs: &'a str,
+        rhs: &'a str,
+        unknowns: &[&str],
+    ) -> anyhow::Result<Expression<'a>> {
+        Ok(Expression::Binary(
+            operator,
+            Box::new(self.expression(lhs, unknowns)?),
+            Box::new(self.expression(rhs, unknowns)?),
+        ))
+    }
 
-        let (with_human, others) = if Self::contains(monkeys, node.lhs().unwrap(), "humn") {
-            (node.lhs().unwrap(), node.rhs().unwrap())
-        } else {
-            (node.rhs().unwrap(), node.lhs().unwrap())
-        };
+    /// Exports the dependency graph of the monkeys in the Graphviz DOT format.
+    pub fn to_dot(&self) -> String {
+        let mut names: Vec<&&str> = self.monkeys.keys().collect();
+        names.sort_unstable();
+        let mut dot = String::from("digraph monkeys {\n");
+        for name in &names {
+            dot.push_str(&format!(
+                "    {} [label=\"{}: {}\"];\n",
+                name, name, self.monkeys[**name]
+            ));
+        }
+        for name in &names {
+            let statement = &self.monkeys[**name];
+            for operand in statement.lhs().into_iter().chain(statement.rhs()) {
+                dot.push_str(&format!("    {} -> {};\n", name, operand));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
 
-        let other_value = Self::eval(monkeys, others);
-        let new_target_value = match node {
-            Statement::Const(_) => panic!(),
-            Statement::Add(_, _) => target_value - other_value,
-            Statement::Sub(lhs, _) => {
-                if *lhs == with_human {
-                    target_value + other_value
-                } else {
-                    other_value - target_value
+    fn statement(&self, node: &str) -> anyhow::Result<&Statement<'a>> {
+        self.monkeys
+            .get(node)
+            .ok_or_else(|| anyhow::anyhow!("undefined monkey {}", node))
+    }
+
+    fn eval(&self, node: &str) -> anyhow::Result<i64> {
+        let overflow = || anyhow::anyhow!("overflow evaluating {}", node);
+        Ok(match *self.statement(node)? {
+            Statement::Const(value) => value,
+            Statement::Add(lhs, rhs) => self
+                .eval(lhs)?
+                .checked_add(self.eval(rhs)?)
+                .ok_or_else(overflow)?,
+            Statement::Sub(lhs, rhs) => self
+                .eval(lhs)?
+                .checked_sub(self.eval(rhs)?)
+                .ok_or_else(overflow)?,
+            Statement::Mul(lhs, rhs) => self
+                .eval(lhs)?
+                .checked_mul(self.eval(rhs)?)
+                .ok_or_else(overflow)?,
+            Statement::Div(lhs, rhs) => {
+                let (lhs, rhs) = (self.eval(lhs)?, self.eval(rhs)?);
+                if rhs == 0 {
+                    return Err(anyhow::anyhow!("division by zero evaluating {}", node));
                 }
-            }
-            Statement::Mul(_, _) => target_value / other_value,
-            Statement::Div(lhs, _) => {
-                if *lhs == with_human {
-                    target_value * other_value
-                } else {
-                    other_value / target_value
+                if lhs % rhs != 0 {
+                    return Err(anyhow::anyhow!("inexact division evaluating {}", node));
                 }
+                lhs.checked_div(rhs).ok_or_else(overflow)?
             }
-        };
-        Self::solve(monkeys, with_human, new_target_value)
+        })
+    }
+
+    /// Solves the equation given by `root` comparing its two operands for
+    /// the monkey `variable`, ignoring the value that monkey would yell.
+    pub fn solve_for(&self, variable: &str) -> anyhow::Result<Rational> {
+        let root = self.statement("root")?;
+        let (lhs, rhs) = root
+            .lhs()
+            .zip(root.rhs())
+            .ok_or_else(|| anyhow::anyhow!("root must compare two monkeys"))?;
+        let difference = self
+            .linearize(lhs, variable)?
+            .sub(self.linearize(rhs, variable)?)?;
+        if difference.coefficient.is_zero() {
+            return Err(if difference.constant.is_zero() {
+                anyhow::anyhow!("{} can take any value", variable)
+            } else {
+                anyhow::anyhow!("no value of {} solves the equation", variable)
+            });
+        }
+        difference
+            .constant
+            .checked_neg()?
+            .checked_div(difference.coefficient)
+    }
+
+    fn linearize(&self, node: &str, variable: &str) -> anyhow::Result<Linear> {
+        if node == variable {
+            return Ok(Linear {
+                coefficient: Rational::ONE,
+                constant: Rational::ZERO,
+            });
+        }
+        match *self.statement(node)? {
+            Statement::Const(value) => Ok(Linear::constant(value.into())),
+            Statement::Add(lhs, rhs) => self
+                .linearize(lhs, variable)?
+                .add(self.linearize(rhs, variable)?),
+            Statement::Sub(lhs, rhs) => self
+                .linearize(lhs, variable)?
+                .sub(self.linearize(rhs, variable)?),
+            Statement::Mul(lhs, rhs) => self
+                .linearize(lhs, variable)?
+                .mul(self.linearize(rhs, variable)?),
+            Statement::Div(lhs, rhs) => self
+                .linearize(lhs, variable)?
+                .div(self.linearize(rhs, variable)?),
+        }
     }
 }
 
@@ -182,4 +572,116 @@ mod tests {
         let input = include_str!("examples/day21");
         test_example_input::<Solver, _, _>(input, 152, Some(301));
     }
+
+    #[test]
+    fn test_solve_for_other_variable() {
+        let input = include_str!("examples/day21");
+        let solver = Solver::new(input).unwrap();
+        assert_eq!(
+            solver.solve_for("dvpt").unwrap(),
+            Rational::new(-293, 1).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_solve_fraction_and_both_sides() {
+        let solver = Solver::new(
+            "root: aaaa + bbbb\naaaa: humn * cccc\nbbbb: humn + dddd\ncccc: 3\ndddd: 1\nhumn: 0\n",
+        )
+        .unwrap();
+        assert_eq!(
+            solver.solve_for("humn").unwrap(),
+            Rational::new(1, 2).unwrap()
+        );
+        assert!(solver.solve_part2().is_err());
+    }
+
+    #[test]
+    fn test_non_linear() {
+        let solver =
+            Solver::new("root: aaaa + bbbb\naaaa: humn * humn\nbbbb: 4\nhumn: 0\n").unwrap();
+        assert!(solver.solve_for("humn").is_err());
+    }
+
+    #[test]
+    fn test_unsolvable() {
+        let solver = Solver::new(
+            "root: aaaa + bbbb\naaaa: humn + humn\nbbbb: humn * cccc\ncccc: 2\nhumn: 0\n",
+        )
+        .unwrap();
+        assert!(solver.solve_for("humn").is_err());
+    }
+
+    #[test]
+    fn test_expression() {
+        let input = include_str!("examples/day21");
+        let solver = Solver::new(input).unwrap();
+        let expression = solver.expression("pppw", &["humn"]).unwrap();
+        assert_eq!(expression.to_string(), "(4 + 2 * (humn - 3)) / 4");
+        assert_eq!(
+            expression.simplify().unwrap().to_string(),
+            "(4 + 2 * (humn - 3)) / 4"
+        );
+        assert_eq!(
+            solver.expression("sjmn", &["humn"]).unwrap().to_string(),
+            "(32 - 2) * 5"
+        );
+        assert_eq!(
+            solver
+                .expression("sjmn", &["humn"])
+                .unwrap()
+                .simplify()
+                .unwrap(),
+            Expression::Constant(150.into())
+        );
+        assert_eq!(
+            solver
+                .expression("root", &["humn", "hmdt"])
+                .unwrap()
+                .simplify()
+                .unwrap()
+                .to_string(),
+            "(4 + 2 * (humn - 3)) / 4 + (hmdt - 2) * 5"
+        );
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let solver = Solver::new("root: aaaa - bbbb\naaaa: 3\nbbbb: 4\n").unwrap();
+        assert_eq!(
+            solver.to_dot(),
+            "digraph monkeys {\n    \
+             aaaa [label=\"aaaa: 3\"];\n    \
+             bbbb [label=\"bbbb: 4\"];\n    \
+             root [label=\"root: aaaa - bbbb\"];\n    \
+             root -> aaaa;\n    \
+             root -> bbbb;\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn test_undefined_reference() {
+        assert!(Solver::new("root: aaaa + bbbb\naaaa: 3\n").is_err());
+    }
+
+    #[test]
+    fn test_cycle() {
+        assert!(
+            Solver::new("root: aaaa + bbbb\naaaa: bbbb * cccc\nbbbb: 1\ncccc: aaaa - bbbb\n")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_eval_inexact_division() {
+        let solver = Solver::new("root: aaaa / bbbb\naaaa: 5\nbbbb: 2\n").unwrap();
+        assert!(solver.solve_part1().is_err());
+    }
+
+    #[test]
+    fn test_eval_overflow() {
+        let solver = Solver::new("root: aaaa * aaaa\naaaa: 9223372036854775807\n").unwrap();
+        assert!(solver.solve_part1().is_err());
+    }
 }
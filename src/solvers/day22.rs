@@ -31,6 +31,15 @@ impl Facing {
             Facing::Up => 3,
         }
     }
+
+    fn symbol(&self) -> char {
+        match self {
+            Facing::Right => '>',
+            Facing::Down => 'v',
+            Facing::Left => '<',
+            Facing::Up => '^',
+        }
+    }
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -276,26 +285,73 @@ impl<'a> Map<'a> {
         (next.0 as usize, next.1 as usize)
     }
 
-    fn edge_length(&self) -> usize {
-        let (long, short) = if self.width > self.map.len() {
-            (self.width, self.map.len())
-        } else {
-            (self.map.len(), self.width)
-        };
-        if long / 4 == short / 3 {
-            long / 4
-        } else if long / 5 == short / 2 {
-            long / 5
-        } else {
-            panic!("not a cube unfolding?");
+    fn edge_length(&self) -> anyhow::Result<usize> {
+        let area: usize = self
+            .map
+            .iter()
+            .map(|row| row.iter().filter(|&&c| c != b' ').count())
+            .sum();
+        let edge_length = (1..)
+            .take_while(|edge_length| 6 * edge_length * edge_length <= area)
+            .last()
+            .unwrap_or_default();
+        if edge_length == 0 || 6 * edge_length * edge_length != area {
+            return Err(anyhow::anyhow!("map area does not match a cube surface"));
         }
+        Ok(edge_length)
+    }
+
+    /// Column of the leftmost cell that is part of the map. The faces of a
+    /// cube net are aligned to this column, which need not be a multiple of
+    /// the edge length.
+    fn first_column(&self) -> usize {
+        self.map
+            .iter()
+            .filter_map(|row| row.iter().position(|&c| c != b' '))
+            .min()
+            .unwrap_or_default()
+    }
+
+    /// Whether the square of the given edge length with its top left corner
+    /// at `origin` is completely part of the map.
+    fn is_square(&self, origin: (usize, usize), edge_length: usize) -> bool {
+        (origin.0..origin.0 + edge_length).all(|row| {
+            self.map
+                .get(row)
+                .and_then(|row| row.get(origin.1..origin.1 + edge_length))
+                .is_some_and(|cells| cells.iter().all(|&c| c != b' '))
+        })
     }
 }
 
+type Trail = Vec<((usize, usize), Facing)>;
+
+/// Draws the trail of visited positions onto the map, the last facing at
+/// each position determines the symbol.
+fn render_trail(map: &Map, trail: &[((usize, usize), Facing)]) -> String {
+    let mut canvas: Vec<Vec<char>> = map
+        .map
+        .iter()
+        .map(|row| row.iter().map(|&c| c as char).collect())
+        .collect();
+    for &(pos, facing) in trail {
+        canvas[pos.0][pos.1] = facing.symbol();
+    }
+    canvas
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .chain(std::iter::once('\n'))
+                .collect::<String>()
+        })
+        .collect()
+}
+
 struct Tracer<'a, 'b> {
     map: &'a Map<'b>,
     pos: (usize, usize),
     facing: Facing,
+    trail: Trail,
 }
 
 impl<'a, 'b> Tracer<'a, 'b> {
@@ -304,6 +360,7 @@ impl<'a, 'b> Tracer<'a, 'b> {
             map,
             pos: map.start(),
             facing: Facing::Right,
+            trail: vec![(map.start(), Facing::Right)],
         }
     }
 
@@ -314,6 +371,7 @@ impl<'a, 'b> Tracer<'a, 'b> {
                     let next = self.map.next_in_direction(self.pos, self.facing);
                     if self.map.is_free(next) {
                         self.pos = next;
+                        self.trail.push((self.pos, self.facing));
                     } else {
                         break;
                     }
@@ -336,11 +394,22 @@ impl<'a, 'b> Tracer<'a, 'b> {
                 }
             }
         }
+        if let Move::Rotation(_) = mv {
+            self.trail.push((self.pos, self.facing));
+        }
     }
 
     pub fn password(&self) -> usize {
         1000 * (self.pos.0 + 1) + 4 * (self.pos.1 + 1) + self.facing.encode()
     }
+
+    pub fn trail(&self) -> &[((usize, usize), Facing)] {
+        &self.trail
+    }
+
+    pub fn render(&self) -> String {
+        render_trail(self.map, self.trail())
+    }
 }
 
 struct CubeTracer<'a, 'b> {
@@ -350,69 +419,65 @@ struct CubeTracer<'a, 'b> {
     facing: Facing,
     face: CubeFace,
     pos: (isize, isize),
+    trail: Trail,
 }
 
 impl<'a, 'b> CubeTracer<'a, 'b> {
-    pub fn from_map(map: &'a Map<'b>) -> Self {
+    /// Folds the map into a cube. Any of the eleven cube nets is supported
+    /// in any rotation or reflection.
+    pub fn from_map(map: &'a Map<'b>) -> anyhow::Result<Self> {
+        let edge_length = map.edge_length()?;
+        let start = map.start();
+        let col_offset = map.first_column();
+        let start_origin = (
+            0,
+            col_offset + (start.1 - col_offset) / edge_length * edge_length,
+        );
+        if !map.is_square(start_origin, edge_length) {
+            return Err(anyhow::anyhow!("map is not a cube net"));
+        }
+
         let mut visited: HashSet<(usize, usize)> = HashSet::new();
         let mut faces: HashMap<CubeFace, (Mat3x3, (usize, usize))> = HashMap::new();
-        let edge_length = map.edge_length();
-
         let mut queue: VecDeque<(Mat3x3, (usize, usize))> = VecDeque::new();
-        visited.insert(map.start());
-        queue.push_back((Mat3x3((1, 0, 0), (0, 1, 0), (0, 0, 1)), map.start()));
+        visited.insert(start_origin);
+        queue.push_back((Mat3x3((1, 0, 0), (0, 1, 0), (0, 0, 1)), start_origin));
         while let Some((orientation, origin)) = queue.pop_front() {
-            let check_pos = (origin.0 + edge_length, origin.1);
-            if !visited.contains(&check_pos)
-                && check_pos.0 < map.map.len()
-                && check_pos.1 < map.map[check_pos.0].len()
-                && map.map[check_pos.0][check_pos.1] != b' '
-            {
-                visited.insert(check_pos);
-                queue.push_back(((FOLD_UP * orientation.inv()).inv(), check_pos));
-            }
+            let mut neighbours = vec![
+                ((origin.0 + edge_length, origin.1), FOLD_UP),
+                ((origin.0, origin.1 + edge_length), FOLD_LEFT),
+            ];
             if origin.0 >= edge_length {
-                let check_pos = (origin.0 - edge_length, origin.1);
-                if !visited.contains(&check_pos)
-                    && check_pos.1 < map.map[check_pos.0].len()
-                    && map.map[check_pos.0][check_pos.1] != b' '
-                {
-                    visited.insert(check_pos);
-                    queue.push_back(((FOLD_DOWN * orientation.inv()).inv(), check_pos));
-                }
-            }
-
-            let check_pos = (origin.0, origin.1 + edge_length);
-            if !visited.contains(&check_pos)
-                && check_pos.0 < map.map.len()
-                && check_pos.1 < map.map[check_pos.0].len()
-                && map.map[check_pos.0][check_pos.1] != b' '
-            {
-                visited.insert(check_pos);
-                queue.push_back(((FOLD_LEFT * orientation.inv()).inv(), check_pos));
+                neighbours.push(((origin.0 - edge_length, origin.1), FOLD_DOWN));
             }
             if origin.1 >= edge_length {
-                let check_pos = (origin.0, origin.1 - edge_length);
-                if !visited.contains(&check_pos) && map.map[check_pos.0][check_pos.1] != b' ' {
+                neighbours.push(((origin.0, origin.1 - edge_length), FOLD_RIGHT));
+            }
+            for (check_pos, folding) in neighbours {
+                if !visited.contains(&check_pos) && map.is_square(check_pos, edge_length) {
                     visited.insert(check_pos);
-                    queue.push_back(((FOLD_RIGHT * orientation.inv()).inv(), check_pos));
+                    queue.push_back(((folding * orientation.inv()).inv(), check_pos));
                 }
             }
 
-            faces.insert(
-                CubeFace::try_from(orientation.2).unwrap(),
-                (orientation, origin),
-            );
+            let face = CubeFace::try_from(orientation.2)?;
+            if faces.insert(face, (orientation, origin)).is_some() {
+                return Err(anyhow::anyhow!("map is not a cube net"));
+            }
+        }
+        if faces.len() != 6 {
+            return Err(anyhow::anyhow!("map is not a cube net"));
         }
 
-        Self {
+        Ok(Self {
             edge_length,
             map,
             faces,
             face: CubeFace::Top,
             facing: Facing::Right,
-            pos: (0, 0),
-        }
+            pos: (0, (start.1 - start_origin.1) as isize),
+            trail: vec![(start, Facing::Right)],
+        })
     }
 
     fn normalize_pos(
@@ -489,6 +554,7 @@ impl<'a, 'b> CubeTracer<'a, 'b> {
                         self.facing = new_facing;
                         self.face = face;
                         self.pos = new_pos;
+                        self.trail.push((self.map_pos(), self.facing));
                     } else {
                         break;
                     }
@@ -511,6 +577,9 @@ impl<'a, 'b> CubeTracer<'a, 'b> {
                 }
             }
         }
+        if let Move::Rotation(_) = mv {
+            self.trail.push((self.map_pos(), self.facing));
+        }
     }
 
     pub fn password(&self) -> usize {
@@ -525,6 +594,49 @@ impl<'a, 'b> CubeTracer<'a, 'b> {
             origin.1 + self.pos.1 as usize,
         )
     }
+
+    pub fn trail(&self) -> &[((usize, usize), Facing)] {
+        &self.trail
+    }
+
+    pub fn render(&self) -> String {
+        render_trail(self.map, self.trail())
+    }
+
+    /// Renders each cube face separately together with the faces adjacent
+    /// to each of its edges.
+    pub fn render_faces(&self) -> String {
+        let el = self.edge_length as isize;
+        let trail_map = self.render();
+        let trail_map: Vec<&[u8]> = trail_map.lines().map(|line| line.as_bytes()).collect();
+        let mut output = String::new();
+        for face in [
+            CubeFace::Top,
+            CubeFace::Bottom,
+            CubeFace::Front,
+            CubeFace::Back,
+            CubeFace::Left,
+            CubeFace::Right,
+        ] {
+            let neighbour = |pos| self.normalize_pos(Facing::Up, (face, pos)).1;
+            output.push_str(&format!(
+                "{:?} (up: {:?}, down: {:?}, left: {:?}, right: {:?})\n",
+                face,
+                neighbour((-1, 0)),
+                neighbour((el, 0)),
+                neighbour((0, -1)),
+                neighbour((0, el)),
+            ));
+            let origin = self.faces.get(&face).unwrap().1;
+            for row in &trail_map[origin.0..origin.0 + self.edge_length] {
+                output.push_str(&String::from_utf8_lossy(
+                    &row[origin.1..origin.1 + self.edge_length],
+                ));
+                output.push('\n');
+            }
+        }
+        output
+    }
 }
 
 pub struct Solver<'a> {
@@ -563,17 +675,33 @@ impl<'a> AocSolver<'a, usize, usize> for Solver<'a> {
     }
 
     fn solve_part2(&self) -> anyhow::Result<Option<usize>> {
-        let mut tracer = CubeTracer::from_map(&self.map);
+        let mut tracer = CubeTracer::from_map(&self.map)?;
         for mv in &self.path {
             tracer.execute_move(*mv);
         }
         Ok(Some(tracer.password()))
     }
+
+    fn visualize(&self) -> anyhow::Result<Option<String>> {
+        let mut tracer = Tracer::new(&self.map);
+        let mut cube_tracer = CubeTracer::from_map(&self.map)?;
+        for mv in &self.path {
+            tracer.execute_move(*mv);
+            cube_tracer.execute_move(*mv);
+        }
+        Ok(Some(format!(
+            "{}\n{}\n{}",
+            tracer.render(),
+            cube_tracer.render(),
+            cube_tracer.render_faces()
+        )))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::solvers::test::test_example_input;
+    use rstest::rstest;
 
     use super::*;
 
@@ -600,4 +728,174 @@ mod tests {
         let input = include_str!("examples/day22");
         test_example_input::<Solver, _, _>(input, 6032, Some(5031));
     }
+
+    #[rstest]
+    #[case(1)]
+    #[case(2)]
+    #[case(5)]
+    fn test_example_offset(#[case] offset: usize) {
+        let input: String = include_str!("examples/day22")
+            .split_inclusive('\n')
+            .map(|line| {
+                if line.trim().is_empty() || line.starts_with(|c: char| c.is_ascii_digit()) {
+                    line.to_string()
+                } else {
+                    " ".repeat(offset) + line
+                }
+            })
+            .collect();
+        test_example_input::<Solver, _, _>(&input, 6032 + 4 * offset, Some(5031 + 4 * offset));
+    }
+
+    #[test]
+    fn test_render_trail() {
+        let input = include_str!("examples/day22");
+        let solver = Solver::new(input).unwrap();
+        let mut tracer = Tracer::new(&solver.map);
+        for mv in &solver.path {
+            tracer.execute_move(*mv);
+        }
+        assert_eq!(tracer.trail().first(), Some(&((0, 8), Facing::Right)));
+        assert_eq!(tracer.trail().last(), Some(&((5, 7), Facing::Right)));
+        assert_eq!(
+            tracer.render(),
+            "        >>v#\n\
+             \x20       .#v.\n\
+             \x20       #.v.\n\
+             \x20       ..v.\n\
+             ...#...v..v#\n\
+             >>>v...>#.>>\n\
+             ..#v...#....\n\
+             ...>>>>v..#.\n\
+             \x20       ...#....\n\
+             \x20       .....#..\n\
+             \x20       .#......\n\
+             \x20       ......#.\n"
+        );
+    }
+
+    #[test]
+    fn test_render_cube_trail() {
+        let input = include_str!("examples/day22");
+        let solver = Solver::new(input).unwrap();
+        let mut tracer = CubeTracer::from_map(&solver.map).unwrap();
+        for mv in &solver.path {
+            tracer.execute_move(*mv);
+        }
+        assert_eq!(tracer.trail().last(), Some(&((4, 6), Facing::Up)));
+        assert_eq!(
+            tracer.render(),
+            "        >>v#\n\
+             \x20       .#v.\n\
+             \x20       #.v.\n\
+             \x20       ..v.\n\
+             ...#..^...v#\n\
+             .>>>>>^.#.>>\n\
+             .^#....#....\n\
+             .^........#.\n\
+             \x20       ...#..v.\n\
+             \x20       .....#v.\n\
+             \x20       .#v<<<<.\n\
+             \x20       ..v...#.\n"
+        );
+        assert!(tracer
+            .render_faces()
+            .starts_with("Top (up: Back, down: Front, left: Left, right: Right)\n>>v#\n"));
+    }
+
+    /// The eleven cube nets with `#` marking a face.
+    static CUBE_NETS: [&[&str]; 11] = [
+        &["#...", "####", "#..."],
+        &["#...", "####", ".#.."],
+        &["#...", "####", "..#."],
+        &["#...", "####", "...#"],
+        &[".#..", "####", ".#.."],
+        &[".#..", "####", "..#."],
+        &["##..", ".###", ".#.."],
+        &["##..", ".###", "..#."],
+        &["##..", ".###", "...#"],
+        &["##..", ".##.", "..##"],
+        &["###..", "..###"],
+    ];
+
+    /// Returns all rotations and reflections of the given tile pattern.
+    fn symmetries(net: &[&str]) -> Vec<Vec<Vec<bool>>> {
+        let mut tiles: Vec<Vec<bool>> = net
+            .iter()
+            .map(|row| row.bytes().map(|c| c == b'#').collect())
+            .collect();
+        let mut result = vec![];
+        for _ in 0..2 {
+            for _ in 0..4 {
+                let rotated: Vec<Vec<bool>> = (0..tiles[0].len())
+                    .map(|col| tiles.iter().rev().map(|row| row[col]).collect())
+                    .collect();
+                result.push(rotated.clone());
+                tiles = rotated;
+            }
+            tiles = tiles.into_iter().rev().collect();
+        }
+        result
+    }
+
+    fn render_net(tiles: &[Vec<bool>], edge_length: usize) -> Vec<Vec<u8>> {
+        tiles
+            .iter()
+            .flat_map(|row| {
+                let line: Vec<u8> = row
+                    .iter()
+                    .flat_map(|&is_face| vec![if is_face { b'.' } else { b' ' }; edge_length])
+                    .collect();
+                vec![line; edge_length]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_all_cube_nets_fold() {
+        let edge_length = 3;
+        for (net, offset) in CUBE_NETS.iter().flat_map(|net| [(net, 0), (net, 2)]) {
+            for tiles in symmetries(net) {
+                let rows: Vec<Vec<u8>> = render_net(&tiles, edge_length)
+                    .into_iter()
+                    .map(|row| [vec![b' '; offset], row].concat())
+                    .collect();
+                let map = Map::new(rows.iter().map(|row| row.as_slice()).collect());
+                let mut tracer = CubeTracer::from_map(&map).unwrap();
+                assert_eq!(tracer.faces.len(), 6);
+
+                let faces: Vec<CubeFace> = tracer.faces.keys().copied().collect();
+                for face in faces {
+                    for facing in [Facing::Up, Facing::Down, Facing::Left, Facing::Right] {
+                        tracer.face = face;
+                        tracer.pos = (1, 2);
+                        tracer.facing = facing;
+                        tracer.execute_move(Move::Forward(4 * edge_length));
+                        assert_eq!(
+                            (tracer.face, tracer.pos, tracer.facing),
+                            (face, (1, 2), facing),
+                            "net {:?}",
+                            tiles
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[rstest]
+    #[case(&["######"])]
+    #[case(&["###", "###"])]
+    #[case(&["####", "#..#"])]
+    #[case(&["#####"])]
+    #[case(&["#...", "####", "#...", "#..."])]
+    fn test_invalid_cube_net(#[case] net: &[&str]) {
+        let tiles: Vec<Vec<bool>> = net
+            .iter()
+            .map(|row| row.bytes().map(|c| c == b'#').collect())
+            .collect();
+        let rows = render_net(&tiles, 2);
+        let map = Map::new(rows.iter().map(|row| row.as_slice()).collect());
+        assert!(CubeTracer::from_map(&map).is_err());
+    }
 }
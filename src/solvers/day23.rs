@@ -77,7 +77,7 @@ impl<'a> AocSolver<'a, usize, usize> for Solver {
                         {
                             let proposal =
                                 (elf.0 + check_directions[0].0, elf.1 + check_directions[0].1);
-                            let proposal_list = proposals.entry(proposal).or_insert_with(Vec::new);
+                            let proposal_list = proposals.entry(proposal).or_default();
                             proposal_list.push(*elf);
                             proposed = true;
                             break;
@@ -1,51 +1,228 @@
-use super::base::AocSolver;
+use std::{
+    fmt::Display,
+    iter::Sum,
+    marker::PhantomData,
+    ops::{Add, Mul, Neg, Sub},
+    str::FromStr,
+};
 
-pub struct Solver {
-    solution: String,
+use super::{base::AocSolver, error::InputParseError};
+
+/// Largest supported base, so that digits fit into an `i8`.
+const MAX_BASE: usize = 2 * i8::MAX as usize + 1;
+
+/// Odd base of a balanced numeral system together with the symbols for its
+/// digits, ordered from the most negative to the most positive digit.
+pub trait BalancedBase {
+    const SYMBOLS: &'static [char];
+    const BASE: i32 = {
+        assert!(
+            Self::SYMBOLS.len() % 2 == 1,
+            "balanced bases need an odd number of symbols"
+        );
+        assert!(Self::SYMBOLS.len() <= MAX_BASE, "base too large");
+        Self::SYMBOLS.len() as i32
+    };
+    const MAX_DIGIT: i32 = Self::BASE / 2;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SnafuBase;
+
+impl BalancedBase for SnafuBase {
+    const SYMBOLS: &'static [char] = &['=', '-', '0', '1', '2'];
+}
+
+/// Number in a balanced numeral system with digits from `-MAX_DIGIT` to
+/// `MAX_DIGIT`. Digits are stored least significant first without leading
+/// zeros, so that zero has no digits.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Balanced<B: BalancedBase> {
+    digits: Vec<i8>,
+    base: PhantomData<B>,
 }
 
-fn parse_snafu(snafu: &str) -> isize {
-    let mut current = 0;
-    for c in snafu.chars() {
-        current *= 5;
-        current += match c {
-            '=' => -2,
-            '-' => -1,
-            '0' => 0,
-            '1' => 1,
-            '2' => 2,
-            _ => 0,
-        };
-    }
-    current
-}
-
-fn to_snafu(mut value: isize) -> String {
-    let mut digits = vec![];
-    let mut carry = 0;
-    while value > 0 || carry > 0 {
-        let digit = value % 5 + carry;
-        value /= 5;
-        if digit > 2 {
-            carry = 1;
-            digits.push(digit - 5)
+pub type Snafu = Balanced<SnafuBase>;
+
+impl<B: BalancedBase> Balanced<B> {
+    fn from_digits(mut digits: Vec<i8>) -> Self {
+        while digits.last() == Some(&0) {
+            digits.pop();
+        }
+        Self {
+            digits,
+            base: PhantomData,
+        }
+    }
+
+    /// Splits a value into a balanced digit and the carry to the next
+    /// position.
+    fn split_carry(value: i32) -> (i8, i32) {
+        let remainder = value.rem_euclid(B::BASE);
+        if remainder > B::MAX_DIGIT {
+            ((remainder - B::BASE) as i8, value.div_euclid(B::BASE) + 1)
         } else {
-            carry = 0;
+            (remainder as i8, value.div_euclid(B::BASE))
+        }
+    }
+
+    fn scale(&self, factor: i8) -> Self {
+        let mut digits = Vec::with_capacity(self.digits.len() + 2);
+        let mut carry = 0;
+        for &digit in &self.digits {
+            let (digit, next_carry) = Self::split_carry(digit as i32 * factor as i32 + carry);
+            digits.push(digit);
+            carry = next_carry;
+        }
+        while carry != 0 {
+            let (digit, next_carry) = Self::split_carry(carry);
+            digits.push(digit);
+            carry = next_carry;
+        }
+        Self::from_digits(digits)
+    }
+}
+
+impl<B: BalancedBase> Default for Balanced<B> {
+    fn default() -> Self {
+        Self::from_digits(vec![])
+    }
+}
+
+impl<B: BalancedBase> FromStr for Balanced<B> {
+    type Err = InputParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err(InputParseError::new("empty number".into()));
+        }
+        let digits = s
+            .chars()
+            .rev()
+            .map(|c| {
+                B::SYMBOLS
+                    .iter()
+                    .position(|&symbol| symbol == c)
+                    .map(|position| (position as i32 - B::MAX_DIGIT) as i8)
+                    .ok_or_else(|| InputParseError::new(format!("invalid digit '{}'", c)))
+            })
+            .collect::<Result<Vec<i8>, InputParseError>>()?;
+        Ok(Self::from_digits(digits))
+    }
+}
+
+impl<B: BalancedBase> Display for Balanced<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.digits.is_empty() {
+            return write!(f, "{}", B::SYMBOLS[B::MAX_DIGIT as usize]);
+        }
+        for &digit in self.digits.iter().rev() {
+            write!(f, "{}", B::SYMBOLS[(digit as i32 + B::MAX_DIGIT) as usize])?;
+        }
+        Ok(())
+    }
+}
+
+impl<B: BalancedBase> Add for Balanced<B> {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        let len = self.digits.len().max(rhs.digits.len());
+        let mut digits = Vec::with_capacity(len + 1);
+        let mut carry = 0;
+        for i in 0..len {
+            let sum = self.digits.get(i).copied().unwrap_or_default() as i32
+                + rhs.digits.get(i).copied().unwrap_or_default() as i32
+                + carry;
+            let (digit, next_carry) = Self::split_carry(sum);
             digits.push(digit);
+            carry = next_carry;
+        }
+        digits.push(carry as i8);
+        Self::from_digits(digits)
+    }
+}
+
+impl<B: BalancedBase> Neg for Balanced<B> {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self::from_digits(self.digits.into_iter().map(|digit| -digit).collect())
+    }
+}
+
+impl<B: BalancedBase> Sub for Balanced<B> {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        self + (-rhs)
+    }
+}
+
+impl<B: BalancedBase> Mul for Balanced<B> {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut product = Self::default();
+        for (shift, &digit) in rhs.digits.iter().enumerate() {
+            let mut partial = self.scale(digit);
+            if !partial.digits.is_empty() {
+                partial.digits.splice(0..0, std::iter::repeat_n(0, shift));
+            }
+            product = product + partial;
+        }
+        product
+    }
+}
+
+impl<B: BalancedBase> Sum for Balanced<B> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), Add::add)
+    }
+}
+
+impl<B: BalancedBase> From<i128> for Balanced<B> {
+    fn from(mut value: i128) -> Self {
+        let base = B::BASE as i128;
+        let mut digits = vec![];
+        while value != 0 {
+            let remainder = value.rem_euclid(base);
+            if remainder > B::MAX_DIGIT as i128 {
+                digits.push((remainder - base) as i8);
+                value = value.div_euclid(base) + 1;
+            } else {
+                digits.push(remainder as i8);
+                value = value.div_euclid(base);
+            }
         }
+        Self::from_digits(digits)
     }
-    digits
-        .iter()
-        .rev()
-        .map(|digit| match digit {
-            -2 => '=',
-            -1 => '-',
-            0 => '0',
-            1 => '1',
-            2 => '2',
-            _ => unreachable!(),
+}
+
+impl<B: BalancedBase> TryFrom<&Balanced<B>> for i128 {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &Balanced<B>) -> Result<Self, Self::Error> {
+        let base = B::BASE as i128;
+        value.digits.iter().rev().try_fold(0i128, |acc, &digit| {
+            let digit = digit as i128;
+            acc.checked_mul(base)
+                .and_then(|acc| acc.checked_add(digit))
+                .or_else(|| {
+                    // Balanced digits may overshoot the range before being
+                    // pulled back by the next digit.
+                    let sign = acc.signum();
+                    (acc - sign)
+                        .checked_mul(base)?
+                        .checked_add(digit + sign * base)
+                })
+                .ok_or_else(|| anyhow::anyhow!("{} does not fit into i128", value))
         })
-        .collect()
+    }
+}
+
+pub struct Solver {
+    solution: String,
 }
 
 impl<'a> AocSolver<'a, String, String> for Solver {
@@ -53,8 +230,15 @@ impl<'a> AocSolver<'a, String, String> for Solver {
     where
         Self: Sized,
     {
+        let sum: Snafu = input
+            .split('\n')
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.trim().parse())
+            .collect::<Result<Vec<Snafu>, InputParseError>>()?
+            .into_iter()
+            .sum();
         Ok(Self {
-            solution: to_snafu(input.split('\n').map(parse_snafu).sum()),
+            solution: sum.to_string(),
         })
     }
 
@@ -71,10 +255,103 @@ impl<'a> AocSolver<'a, String, String> for Solver {
 mod tests {
     use super::*;
     use crate::solvers::test::test_example_input;
+    use rstest::rstest;
 
     #[test]
     fn test_example() {
         let input = include_str!("examples/day25");
         test_example_input::<Solver, _, _>(input, "2=-1=0".into(), None);
     }
+
+    #[rstest]
+    #[case(0, "0")]
+    #[case(1, "1")]
+    #[case(3, "1=")]
+    #[case(8, "2=")]
+    #[case(2022, "1=11-2")]
+    #[case(314159265, "1121-1110-1=0")]
+    #[case(-1, "-")]
+    #[case(-2022, "-2--1=")]
+    fn test_conversion(#[case] value: i128, #[case] snafu: &str) {
+        assert_eq!(Snafu::from(value).to_string(), snafu);
+        assert_eq!(
+            i128::try_from(&snafu.parse::<Snafu>().unwrap()).unwrap(),
+            value
+        );
+    }
+
+    #[test]
+    fn test_extreme_values() {
+        for value in [i128::MIN, i128::MAX] {
+            assert_eq!(i128::try_from(&Snafu::from(value)).unwrap(), value);
+        }
+        assert!(i128::try_from(&(Snafu::from(i128::MAX) + Snafu::from(1))).is_err());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!("".parse::<Snafu>().is_err());
+        assert!("12a".parse::<Snafu>().is_err());
+        assert_eq!("002".parse::<Snafu>().unwrap().to_string(), "2");
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let values: [i128; 7] = [0, 1, -7, 42, 2022, -31415, 1 << 60];
+        for &a in &values {
+            for &b in &values {
+                let (x, y) = (Snafu::from(a), Snafu::from(b));
+                assert_eq!(x.clone() + y.clone(), Snafu::from(a + b));
+                assert_eq!(x.clone() - y.clone(), Snafu::from(a - b));
+                assert_eq!(x.clone() * y, Snafu::from(a.wrapping_mul(b)));
+                assert_eq!(-x, Snafu::from(-a));
+            }
+        }
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    struct TernaryBase;
+
+    impl BalancedBase for TernaryBase {
+        const SYMBOLS: &'static [char] = &['-', '0', '+'];
+    }
+
+    #[test]
+    fn test_balanced_ternary() {
+        type Ternary = Balanced<TernaryBase>;
+        assert_eq!(Ternary::from(8).to_string(), "+0-");
+        assert_eq!(Ternary::from(-8).to_string(), "-0+");
+        assert_eq!(
+            Ternary::from(8) * Ternary::from(-5),
+            "++++".parse::<Ternary>().unwrap() * Ternary::from(-1)
+        );
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    struct LargeBase;
+
+    impl BalancedBase for LargeBase {
+        const SYMBOLS: &'static [char] = &[
+            'r', 'q', 'p', 'o', 'n', 'm', 'l', 'k', 'j', 'i', 'h', 'g', 'f', 'e', 'd', 'c', 'b',
+            'a', '0', 'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O',
+            'P', 'Q', 'R',
+        ];
+    }
+
+    #[test]
+    fn test_large_base() {
+        type Large = Balanced<LargeBase>;
+        assert_eq!(Large::from(18).to_string(), "R");
+        assert_eq!(Large::from(19).to_string(), "Ar");
+        let values: [i128; 6] = [0, 18, -37, 12345, -987654321, 1 << 60];
+        for &a in &values {
+            let x = Large::from(a);
+            assert_eq!(x.to_string().parse::<Large>().unwrap(), x);
+            for &b in &values {
+                let y = Large::from(b);
+                assert_eq!(x.clone() + y.clone(), Large::from(a + b));
+                assert_eq!(x.clone() * y, Large::from(a.wrapping_mul(b)));
+            }
+        }
+    }
 }
@@ -0,0 +1,275 @@
+/// Integer-like type, whose values can be enumerated in order.
+pub trait Discrete: Copy + Ord {
+    fn successor(self) -> Option<Self>;
+    fn predecessor(self) -> Option<Self>;
+    /// Number of values from `self` up to and including `end`.
+    fn count_to(self, end: Self) -> usize;
+}
+
+macro_rules! impl_discrete {
+    ($($t:ty),*) => {
+        $(
+            impl Discrete for $t {
+                fn successor(self) -> Option<Self> {
+                    self.checked_add(1)
+                }
+
+                fn predecessor(self) -> Option<Self> {
+                    self.checked_sub(1)
+                }
+
+                fn count_to(self, end: Self) -> usize {
+                    if end < self {
+                        0
+                    } else {
+                        (end as i128 - self as i128 + 1) as usize
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_discrete!(i32, i64, u32, u64, usize);
+
+/// Interval with inclusive bounds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Interval<T> {
+    pub start: T,
+    pub end: T,
+}
+
+impl<T: Discrete> Interval<T> {
+    pub fn new(start: T, end: T) -> Self {
+        Self { start, end }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.end < self.start
+    }
+
+    pub fn len(&self) -> usize {
+        self.start.count_to(self.end)
+    }
+
+    pub fn includes_fully(&self, other: &Self) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    /// Whether `self` ends before `other` starts with at least one value in
+    /// between, so that the two cannot be merged.
+    fn is_separated_before(&self, other: &Self) -> bool {
+        self.end
+            .successor()
+            .is_some_and(|after_end| after_end < other.start)
+    }
+}
+
+/// Set of values stored as sorted, disjoint and non-adjacent intervals.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IntervalSet<T> {
+    intervals: Vec<Interval<T>>,
+}
+
+impl<T: Discrete> Default for IntervalSet<T> {
+    fn default() -> Self {
+        Self { intervals: vec![] }
+    }
+}
+
+impl<T: Discrete> IntervalSet<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn intervals(&self) -> &[Interval<T>] {
+        &self.intervals
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    /// Total number of values covered.
+    pub fn len(&self) -> usize {
+        self.intervals.iter().map(Interval::len).sum()
+    }
+
+    pub fn insert(&mut self, interval: Interval<T>) {
+        if interval.is_empty() {
+            return;
+        }
+        let mut merged = interval;
+        let mut intervals = Vec::with_capacity(self.intervals.len() + 1);
+        let mut inserted = false;
+        for &existing in &self.intervals {
+            if existing.is_separated_before(&merged) {
+                intervals.push(existing);
+            } else if merged.is_separated_before(&existing) {
+                if !inserted {
+                    intervals.push(merged);
+                    inserted = true;
+                }
+                intervals.push(existing);
+            } else {
+                merged = Interval::new(
+                    merged.start.min(existing.start),
+                    merged.end.max(existing.end),
+                );
+            }
+        }
+        if !inserted {
+            intervals.push(merged);
+        }
+        self.intervals = intervals;
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        let mut union = self.clone();
+        for &interval in &other.intervals {
+            union.insert(interval);
+        }
+        union
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut intervals = vec![];
+        let (mut i, mut j) = (0, 0);
+        while i < self.intervals.len() && j < other.intervals.len() {
+            let (a, b) = (self.intervals[i], other.intervals[j]);
+            let overlap = Interval::new(a.start.max(b.start), a.end.min(b.end));
+            if !overlap.is_empty() {
+                intervals.push(overlap);
+            }
+            if a.end < b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        Self { intervals }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut intervals = vec![];
+        for &interval in &self.intervals {
+            let mut rest = Some(interval.start);
+            for removed in other
+                .intervals
+                .iter()
+                .filter(|removed| removed.overlaps(&interval))
+            {
+                let Some(start) = rest else {
+                    break;
+                };
+                if start < removed.start {
+                    if let Some(end) = removed.start.predecessor() {
+                        intervals.push(Interval::new(start, end));
+                    }
+                }
+                rest = removed.end.successor().filter(|&next| next <= interval.end);
+            }
+            if let Some(start) = rest {
+                intervals.push(Interval::new(start, interval.end));
+            }
+        }
+        Self { intervals }
+    }
+
+    /// Intervals between the first and last value that are not covered.
+    pub fn gaps(&self) -> impl Iterator<Item = Interval<T>> + '_ {
+        self.intervals.windows(2).filter_map(|pair| {
+            Some(Interval::new(
+                pair[0].end.successor()?,
+                pair[1].start.predecessor()?,
+            ))
+        })
+    }
+}
+
+impl<T: Discrete> FromIterator<Interval<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Interval<T>>>(iter: I) -> Self {
+        let mut set = Self::new();
+        for interval in iter {
+            set.insert(interval);
+        }
+        set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    fn set(intervals: &[(i64, i64)]) -> IntervalSet<i64> {
+        intervals
+            .iter()
+            .map(|&(start, end)| Interval::new(start, end))
+            .collect()
+    }
+
+    fn bounds(set: &IntervalSet<i64>) -> Vec<(i64, i64)> {
+        set.intervals()
+            .iter()
+            .map(|interval| (interval.start, interval.end))
+            .collect()
+    }
+
+    #[rstest]
+    #[case(&[(1, 3), (5, 7)], &[(1, 3), (5, 7)])]
+    #[case(&[(5, 7), (1, 3)], &[(1, 3), (5, 7)])]
+    #[case(&[(1, 3), (4, 7)], &[(1, 7)])]
+    #[case(&[(1, 3), (2, 7), (9, 9), (-2, 0)], &[(-2, 7), (9, 9)])]
+    #[case(&[(1, 10), (3, 4)], &[(1, 10)])]
+    #[case(&[(3, 2)], &[])]
+    fn test_insert(#[case] intervals: &[(i64, i64)], #[case] expected: &[(i64, i64)]) {
+        assert_eq!(bounds(&set(intervals)), expected);
+    }
+
+    #[test]
+    fn test_set_operations() {
+        let a = set(&[(0, 5), (10, 15)]);
+        let b = set(&[(3, 12), (20, 20)]);
+        assert_eq!(bounds(&a.union(&b)), vec![(0, 15), (20, 20)]);
+        assert_eq!(bounds(&a.intersection(&b)), vec![(3, 5), (10, 12)]);
+        assert_eq!(bounds(&a.difference(&b)), vec![(0, 2), (13, 15)]);
+        assert_eq!(bounds(&b.difference(&a)), vec![(6, 9), (20, 20)]);
+        assert!(a.difference(&a).is_empty());
+        assert_eq!(a.len(), 12);
+        assert_eq!(
+            bounds(&set(&[(0, 10)]).difference(&set(&[(2, 2), (4, 5), (10, 12)]))),
+            vec![(0, 1), (3, 3), (6, 9)]
+        );
+    }
+
+    #[test]
+    fn test_gaps() {
+        let gaps: Vec<Interval<i64>> = set(&[(0, 2), (5, 5), (7, 9)]).gaps().collect();
+        assert_eq!(gaps, vec![Interval::new(3, 4), Interval::new(6, 6)]);
+        assert_eq!(set(&[(0, 2)]).gaps().count(), 0);
+    }
+
+    #[test]
+    fn test_extreme_bounds() {
+        let full: IntervalSet<u32> = [Interval::new(0, u32::MAX)].into_iter().collect();
+        assert_eq!(full.len(), u32::MAX as usize + 1);
+        let rest = full.difference(&[Interval::new(0, 0)].into_iter().collect());
+        assert_eq!(rest.intervals(), &[Interval::new(1, u32::MAX)]);
+        let rest = full.difference(&[Interval::new(u32::MAX, u32::MAX)].into_iter().collect());
+        assert_eq!(rest.intervals(), &[Interval::new(0, u32::MAX - 1)]);
+    }
+
+    #[test]
+    fn test_interval_relations() {
+        let interval = Interval::new(2, 8);
+        assert!(interval.includes_fully(&Interval::new(3, 7)));
+        assert!(!interval.includes_fully(&Interval::new(3, 9)));
+        assert!(interval.overlaps(&Interval::new(8, 9)));
+        assert!(!interval.overlaps(&Interval::new(9, 9)));
+    }
+}
@@ -25,6 +25,7 @@ pub mod day23;
 pub mod day24;
 pub mod day25;
 pub mod error;
+pub mod interval;
 
 #[cfg(test)]
 pub mod test;